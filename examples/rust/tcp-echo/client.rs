@@ -0,0 +1,158 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    TlsConfig,
+    AF_INET,
+    SOCK_STREAM,
+};
+use anyhow::Result;
+use demikernel::{
+    runtime::types::{
+        demi_opcode_t,
+        demi_qresult_t,
+        demi_sgarray_t,
+    },
+    LibOS,
+    QDesc,
+    QToken,
+};
+use std::net::SocketAddrV4;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// TCP echo client.
+///
+/// Connects to a [`TcpEchoServer`](super::server::TcpEchoServer), then repeatedly pushes a
+/// `bufsize`-byte request and pops the echoed response back.
+pub struct TcpEchoClient {
+    /// Underlying libOS.
+    libos: LibOS,
+    /// Size (in bytes) of each request.
+    bufsize: usize,
+    /// Address of the remote echo server.
+    remote: SocketAddrV4,
+    /// Whether `TCP_NODELAY` was requested on the connection.
+    nodelay: bool,
+    /// TLS configuration, if `--tls` was passed.
+    tls_config: Option<TlsConfig>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl TcpEchoClient {
+    /// Creates a new TCP echo client that will connect to `remote`.
+    pub fn new(
+        libos: LibOS,
+        bufsize: usize,
+        remote: SocketAddrV4,
+        nodelay: bool,
+        tls_config: Option<TlsConfig>,
+    ) -> Result<Self> {
+        Ok(Self {
+            libos,
+            bufsize,
+            remote,
+            nodelay,
+            tls_config,
+        })
+    }
+
+    /// Connects to the server and issues `nrequests` request/echo round trips (or forever, if
+    /// `None`), logging progress every `log_interval` requests.
+    pub fn run(&mut self, log_interval: Option<u64>, nrequests: Option<usize>) -> Result<()> {
+        let sockqd: QDesc = self.libos.socket(AF_INET, SOCK_STREAM, 0)?;
+        if self.nodelay {
+            self.libos.setsockopt(sockqd, libc::IPPROTO_TCP, libc::TCP_NODELAY, 1i32)?;
+        }
+        let qd: QDesc = self.connect_and_wait(sockqd)?;
+        println!("Connected to: {:?}", self.remote);
+
+        let echo_qd: QDesc = match self.tls_config.clone() {
+            Some(tls_config) => {
+                let client_config = demikernel::tls::ClientConfig::from_pem(tls_config.cert())?;
+                self.tls_connect_and_wait(qd, client_config)?
+            },
+            None => qd,
+        };
+
+        let mut nrequests_sent: usize = 0;
+        loop {
+            if let Some(nrequests) = nrequests {
+                if nrequests_sent >= nrequests {
+                    break;
+                }
+            }
+
+            let sga: demi_sgarray_t = self.libos.sgaalloc(self.bufsize)?;
+            self.push_and_wait(echo_qd, &sga)?;
+            self.libos.sgafree(sga)?;
+
+            let reply: demi_sgarray_t = self.pop_and_wait(echo_qd)?;
+            self.libos.sgafree(reply)?;
+
+            nrequests_sent += 1;
+            if let Some(log_interval) = log_interval {
+                if nrequests_sent as u64 % log_interval == 0 {
+                    println!("{} requests sent", nrequests_sent);
+                }
+            }
+        }
+
+        self.libos.close(qd)?;
+        Ok(())
+    }
+
+    /// Connects `sockqd` to `self.remote`, blocking until the connection is established.
+    fn connect_and_wait(&mut self, sockqd: QDesc) -> Result<QDesc> {
+        let qt: QToken = self.libos.connect(sockqd, self.remote)?;
+        let qr: demi_qresult_t = self.libos.wait(qt, None)?;
+        match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_CONNECT => Ok(sockqd),
+            demi_opcode_t::DEMI_OPC_FAILED => anyhow::bail!("connect failed (qr_ret={:?})", qr.qr_ret),
+            qr_opcode => anyhow::bail!("unexpected result (qr_opcode={:?})", qr_opcode),
+        }
+    }
+
+    /// Completes the opportunistic TLS handshake on `qd`, returning the handle push/pop should use
+    /// from here on.
+    fn tls_connect_and_wait(&mut self, qd: QDesc, client_config: demikernel::tls::ClientConfig) -> Result<QDesc> {
+        let qt: QToken = self.libos.tls_connect(qd, "tcp-echo", client_config)?;
+        let qr: demi_qresult_t = self.libos.wait(qt, None)?;
+        match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_TLS_HANDSHAKE => Ok(unsafe { qr.qr_value.ares.qd.into() }),
+            demi_opcode_t::DEMI_OPC_FAILED => anyhow::bail!("TLS handshake failed (qr_ret={:?})", qr.qr_ret),
+            qr_opcode => anyhow::bail!("unexpected result (qr_opcode={:?})", qr_opcode),
+        }
+    }
+
+    /// Pops the next chunk of data off `qd`, blocking until it arrives.
+    fn pop_and_wait(&mut self, qd: QDesc) -> Result<demi_sgarray_t> {
+        let qt: QToken = self.libos.pop(qd, None)?;
+        let qr: demi_qresult_t = self.libos.wait(qt, None)?;
+        match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_POP => Ok(unsafe { qr.qr_value.sga }),
+            demi_opcode_t::DEMI_OPC_FAILED => anyhow::bail!("pop failed (qr_ret={:?})", qr.qr_ret),
+            qr_opcode => anyhow::bail!("unexpected result (qr_opcode={:?})", qr_opcode),
+        }
+    }
+
+    /// Pushes `sga` onto `qd`, blocking until the push completes.
+    fn push_and_wait(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<()> {
+        let qt: QToken = self.libos.push(qd, sga)?;
+        let qr: demi_qresult_t = self.libos.wait(qt, None)?;
+        match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_PUSH => Ok(()),
+            demi_opcode_t::DEMI_OPC_FAILED => anyhow::bail!("push failed (qr_ret={:?})", qr.qr_ret),
+            qr_opcode => anyhow::bail!("unexpected result (qr_opcode={:?})", qr_opcode),
+        }
+    }
+}
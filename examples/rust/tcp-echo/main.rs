@@ -24,6 +24,7 @@ use demikernel::{
 use server::TcpEchoServer;
 use std::{
     net::SocketAddrV4,
+    path::PathBuf,
     str::FromStr,
 };
 
@@ -42,6 +43,37 @@ pub const SOCK_STREAM: i32 = libc::SOCK_STREAM;
 mod client;
 mod server;
 
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Opportunistic TLS configuration for the echo socket.
+///
+/// Bundles exactly the two things a handshake needs and nothing else: the server presents `cert`
+/// to clients and authenticates with `key`; `ProgramArguments` only builds one when `--tls` is
+/// passed, so a plaintext run never touches this path. When present, `TcpEchoServer`/
+/// `TcpEchoClient` wrap the handshaken TCP `QDesc` in a TLS handle (`tls_accept`/`tls_connect`)
+/// instead of pushing/popping it directly.
+#[derive(Clone, Debug)]
+pub struct TlsConfig {
+    /// Path to the PEM-encoded certificate chain (server: presented to clients).
+    cert: PathBuf,
+    /// Path to the PEM-encoded private key matching `cert`.
+    key: PathBuf,
+}
+
+impl TlsConfig {
+    /// Returns the path to the PEM-encoded certificate chain.
+    pub fn cert(&self) -> &PathBuf {
+        &self.cert
+    }
+
+    /// Returns the path to the PEM-encoded private key.
+    pub fn key(&self) -> &PathBuf {
+        &self.key
+    }
+}
+
 //======================================================================================================================
 // Program Arguments
 //======================================================================================================================
@@ -59,6 +91,10 @@ pub struct ProgramArguments {
     log_interval: Option<u64>,
     /// Peer type.
     peer_type: String,
+    /// Whether to disable Nagle coalescing on the TCP socket (`TCP_NODELAY`).
+    nodelay: bool,
+    /// TLS configuration, if `--tls` was passed.
+    tls_config: Option<TlsConfig>,
 }
 
 /// Associate functions for Program Arguments
@@ -109,6 +145,36 @@ impl ProgramArguments {
                     .value_name("INTERVAL")
                     .help("Enables logging"),
             )
+            .arg(
+                Arg::new("nodelay")
+                    .long("nodelay")
+                    .action(clap::ArgAction::SetTrue)
+                    .help("Disables Nagle coalescing on the TCP socket (sets TCP_NODELAY)"),
+            )
+            .arg(
+                Arg::new("tls")
+                    .long("tls")
+                    .action(clap::ArgAction::SetTrue)
+                    .requires("cert")
+                    .requires("key")
+                    .help("Wraps the echo socket in opportunistic TLS (requires --cert and --key)"),
+            )
+            .arg(
+                Arg::new("cert")
+                    .long("cert")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(false)
+                    .value_name("PATH")
+                    .help("Sets the path to the PEM-encoded certificate chain for --tls"),
+            )
+            .arg(
+                Arg::new("key")
+                    .long("key")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(false)
+                    .value_name("PATH")
+                    .help("Sets the path to the PEM-encoded private key for --tls"),
+            )
             .get_matches();
 
         // Default arguments.
@@ -118,8 +184,23 @@ impl ProgramArguments {
             nrequests: None,
             log_interval: None,
             peer_type: "server".to_string(),
+            nodelay: matches.get_flag("nodelay"),
+            tls_config: None,
         };
 
+        // TLS configuration.
+        if matches.get_flag("tls") {
+            let cert: PathBuf = matches
+                .get_one::<PathBuf>("cert")
+                .ok_or(anyhow::anyhow!("missing --cert"))?
+                .clone();
+            let key: PathBuf = matches
+                .get_one::<PathBuf>("key")
+                .ok_or(anyhow::anyhow!("missing --key"))?
+                .clone();
+            args.tls_config = Some(TlsConfig { cert, key });
+        }
+
         // Socket address.
         if let Some(addr) = matches.get_one::<String>("addr") {
             let ref mut this = args;
@@ -188,7 +269,8 @@ fn main() -> Result<()> {
 
     match args.peer_type.as_str() {
         "server" => {
-            let mut server: TcpEchoServer = TcpEchoServer::new(libos, args.saddr.unwrap())?;
+            let mut server: TcpEchoServer =
+                TcpEchoServer::new(libos, args.saddr.unwrap(), args.nodelay, args.tls_config.clone())?;
             server.run(args.log_interval, args.nrequests)?;
         },
         "client" => {
@@ -196,6 +278,8 @@ fn main() -> Result<()> {
                 libos,
                 args.bufsize.ok_or(anyhow::anyhow!("missing buffer size"))?,
                 args.saddr.unwrap(),
+                args.nodelay,
+                args.tls_config.clone(),
             )?;
             client.run(args.log_interval, args.nrequests)?;
         },
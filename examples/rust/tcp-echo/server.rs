@@ -0,0 +1,172 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::{
+    TlsConfig,
+    AF_INET,
+    SOCK_STREAM,
+};
+use anyhow::Result;
+use demikernel::{
+    runtime::types::{
+        demi_opcode_t,
+        demi_qresult_t,
+        demi_sgarray_t,
+    },
+    LibOS,
+    QDesc,
+    QToken,
+};
+use std::net::SocketAddrV4;
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// TCP echo server.
+///
+/// Accepts connections one at a time off `sockqd` and echoes back whatever it pops from the
+/// connected peer, until that peer closes the connection or `nrequests` echoes have been served.
+pub struct TcpEchoServer {
+    /// Underlying libOS.
+    libos: LibOS,
+    /// Listening socket.
+    sockqd: QDesc,
+    /// TLS configuration, if `--tls` was passed.
+    tls_config: Option<TlsConfig>,
+}
+
+//======================================================================================================================
+// Associated Functions
+//======================================================================================================================
+
+impl TcpEchoServer {
+    /// Creates a new TCP echo server bound and listening on `local`. If `nodelay` is set, disables
+    /// Nagle coalescing (`TCP_NODELAY`) on accepted connections. If `tls_config` is set, every
+    /// accepted connection is wrapped in opportunistic TLS before the echo loop touches it.
+    pub fn new(
+        mut libos: LibOS,
+        local: SocketAddrV4,
+        nodelay: bool,
+        tls_config: Option<TlsConfig>,
+    ) -> Result<Self> {
+        let sockqd: QDesc = libos.socket(AF_INET, SOCK_STREAM, 0)?;
+        if nodelay {
+            libos.setsockopt(sockqd, libc::IPPROTO_TCP, libc::TCP_NODELAY, 1i32)?;
+        }
+        libos.bind(sockqd, local)?;
+        libos.listen(sockqd, 16)?;
+        println!("Listening on: {:?}", local);
+        Ok(Self {
+            libos,
+            sockqd,
+            tls_config,
+        })
+    }
+
+    /// Accepts connections and echoes traffic back on each of them, one connection at a time,
+    /// serving up to `nrequests` echoes per connection (no limit, if `None`). Only returns on error:
+    /// once a connection is done it accepts the next one.
+    pub fn run(&mut self, log_interval: Option<u64>, nrequests: Option<usize>) -> Result<()> {
+        loop {
+            let qd: QDesc = self.accept_and_wait()?;
+            println!("Connection accepted");
+
+            let echo_qd: QDesc = match self.tls_config.clone() {
+                Some(tls_config) => {
+                    let server_config = demikernel::tls::ServerConfig::from_pem(tls_config.cert(), tls_config.key())?;
+                    self.tls_accept_and_wait(qd, server_config)?
+                },
+                None => qd,
+            };
+
+            self.echo(echo_qd, log_interval, nrequests)?;
+            self.libos.close(qd)?;
+        }
+    }
+
+    /// Echo loop over `qd` (plaintext, or already wrapped in a TLS handle): pops a request and
+    /// pushes it straight back.
+    fn echo(&mut self, qd: QDesc, log_interval: Option<u64>, nrequests: Option<usize>) -> Result<()> {
+        let mut nrequests_served: usize = 0;
+        loop {
+            if let Some(nrequests) = nrequests {
+                if nrequests_served >= nrequests {
+                    return Ok(());
+                }
+            }
+
+            let sga: demi_sgarray_t = match self.pop_and_wait(qd)? {
+                Some(sga) => sga,
+                None => return Ok(()),
+            };
+            self.push_and_wait(qd, &sga)?;
+            self.libos.sgafree(sga)?;
+
+            nrequests_served += 1;
+            if let Some(log_interval) = log_interval {
+                if nrequests_served as u64 % log_interval == 0 {
+                    println!("{} requests served", nrequests_served);
+                }
+            }
+        }
+    }
+
+    /// Accepts one connection on `self.sockqd`, blocking until it is established.
+    fn accept_and_wait(&mut self) -> Result<QDesc> {
+        let qt: QToken = self.libos.accept(self.sockqd)?;
+        let qr: demi_qresult_t = self.libos.wait(qt, None)?;
+        match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_ACCEPT => Ok(unsafe { qr.qr_value.ares.qd.into() }),
+            demi_opcode_t::DEMI_OPC_FAILED => anyhow::bail!("accept failed (qr_ret={:?})", qr.qr_ret),
+            qr_opcode => anyhow::bail!("unexpected result (qr_opcode={:?})", qr_opcode),
+        }
+    }
+
+    /// Completes the opportunistic TLS handshake on `qd`, returning the handle push/pop should use
+    /// from here on.
+    fn tls_accept_and_wait(&mut self, qd: QDesc, server_config: demikernel::tls::ServerConfig) -> Result<QDesc> {
+        let qt: QToken = self.libos.tls_accept(qd, server_config)?;
+        let qr: demi_qresult_t = self.libos.wait(qt, None)?;
+        match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_TLS_HANDSHAKE => Ok(unsafe { qr.qr_value.ares.qd.into() }),
+            demi_opcode_t::DEMI_OPC_FAILED => anyhow::bail!("TLS handshake failed (qr_ret={:?})", qr.qr_ret),
+            qr_opcode => anyhow::bail!("unexpected result (qr_opcode={:?})", qr_opcode),
+        }
+    }
+
+    /// Pops the next chunk of data off `qd`, returning `None` once the peer has closed the
+    /// connection.
+    fn pop_and_wait(&mut self, qd: QDesc) -> Result<Option<demi_sgarray_t>> {
+        let qt: QToken = self.libos.pop(qd, None)?;
+        let qr: demi_qresult_t = self.libos.wait(qt, None)?;
+        match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_POP => {
+                let sga: demi_sgarray_t = unsafe { qr.qr_value.sga };
+                if sga.sga_segs[0].sgaseg_len == 0 {
+                    self.libos.sgafree(sga)?;
+                    Ok(None)
+                } else {
+                    Ok(Some(sga))
+                }
+            },
+            demi_opcode_t::DEMI_OPC_FAILED => anyhow::bail!("pop failed (qr_ret={:?})", qr.qr_ret),
+            qr_opcode => anyhow::bail!("unexpected result (qr_opcode={:?})", qr_opcode),
+        }
+    }
+
+    /// Pushes `sga` onto `qd`, blocking until the push completes.
+    fn push_and_wait(&mut self, qd: QDesc, sga: &demi_sgarray_t) -> Result<()> {
+        let qt: QToken = self.libos.push(qd, sga)?;
+        let qr: demi_qresult_t = self.libos.wait(qt, None)?;
+        match qr.qr_opcode {
+            demi_opcode_t::DEMI_OPC_PUSH => Ok(()),
+            demi_opcode_t::DEMI_OPC_FAILED => anyhow::bail!("push failed (qr_ret={:?})", qr.qr_ret),
+            qr_opcode => anyhow::bail!("unexpected result (qr_opcode={:?})", qr_opcode),
+        }
+    }
+}
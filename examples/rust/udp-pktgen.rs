@@ -16,6 +16,7 @@ use ::clap::{
     Arg,
     ArgMatches,
     Command,
+    ValueEnum,
 };
 use ::demikernel::{
     demi_sgarray_t,
@@ -26,7 +27,11 @@ use ::demikernel::{
     QToken,
 };
 use ::std::{
-    net::SocketAddrV4,
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+        SocketAddrV4,
+    },
     slice,
     str::FromStr,
     time::{
@@ -35,6 +40,34 @@ use ::std::{
     },
 };
 
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// `AF_INET6`, paired with the `libc::AF_INET` socket creation already in use: the transport
+/// socket must be created in the address family matching `--local`/`--remote`.
+#[cfg(target_os = "linux")]
+const AF_INET6: i32 = libc::AF_INET6;
+#[cfg(target_os = "windows")]
+const AF_INET6: i32 = windows::Win32::Networking::WinSock::AF_INET6.0 as i32;
+
+/// `IPPROTO_QUIC`, mirroring the value `InetStack::socket` dispatches on to hand a `SOCK_DGRAM`
+/// socket to the QUIC peer instead of the plain UDP datapath.
+const IPPROTO_QUIC: i32 = 261;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Transport to generate traffic over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TransportMode {
+    /// Plain, connectionless UDP datagrams.
+    Udp,
+    /// A single QUIC connection multiplexed over the same UDP socket.
+    Quic,
+}
+
 //==============================================================================
 // Program Arguments
 //==============================================================================
@@ -42,14 +75,18 @@ use ::std::{
 /// Program Arguments
 #[derive(Debug)]
 pub struct ProgramArguments {
-    /// Local socket IPv4 address.
-    local: SocketAddrV4,
-    /// Remote socket IPv4 address.
-    remote: SocketAddrV4,
+    /// Local socket address, either IPv4 or (bracketed) IPv6.
+    local: SocketAddr,
+    /// Remote socket address, either IPv4 or (bracketed) IPv6.
+    remote: SocketAddr,
     /// Buffer size (in bytes).
     bufsize: usize,
     /// Injection rate (in micro-seconds).
     injection_rate: u64,
+    /// Transport to generate traffic over.
+    transport: TransportMode,
+    /// Multicast group to join and flood to, if any.
+    multicast: Option<Ipv4Addr>,
 }
 
 /// Associate functions for Program Arguments
@@ -100,14 +137,33 @@ impl ProgramArguments {
                     .value_name("RATE")
                     .help("Sets packet injection rate"),
             )
+            .arg(
+                Arg::new("transport")
+                    .long("transport")
+                    .value_parser(clap::value_parser!(TransportMode))
+                    .required(false)
+                    .value_name("udp|quic")
+                    .default_value("udp")
+                    .help("Sets the transport to generate traffic over"),
+            )
+            .arg(
+                Arg::new("multicast")
+                    .long("multicast")
+                    .value_parser(clap::value_parser!(Ipv4Addr))
+                    .required(false)
+                    .value_name("GROUP")
+                    .help("Joins a multicast group and, with --transport udp, floods traffic to it instead of --remote"),
+            )
             .get_matches();
 
         // Default arguments.
         let mut args: ProgramArguments = ProgramArguments {
-            local: SocketAddrV4::from_str(Self::DEFAULT_LOCAL)?,
-            remote: SocketAddrV4::from_str(Self::DEFAULT_REMOTE)?,
+            local: SocketAddr::V4(SocketAddrV4::from_str(Self::DEFAULT_LOCAL)?),
+            remote: SocketAddr::V4(SocketAddrV4::from_str(Self::DEFAULT_REMOTE)?),
             bufsize: Self::DEFAULT_BUFSIZE,
             injection_rate: Self::DEFAULT_INJECTION_RATE,
+            transport: TransportMode::Udp,
+            multicast: None,
         };
 
         // Local address.
@@ -130,16 +186,29 @@ impl ProgramArguments {
             args.set_injection_rate(injection_rate)?;
         }
 
+        // Transport.
+        if let Some(transport) = matches.get_one::<TransportMode>("transport") {
+            args.transport = *transport;
+        }
+
+        // Multicast group.
+        if let Some(group) = matches.get_one::<Ipv4Addr>("multicast") {
+            if !group.is_multicast() {
+                bail!("not a multicast address: {}", group);
+            }
+            args.multicast = Some(*group);
+        }
+
         Ok(args)
     }
 
     /// Returns the local endpoint address parameter stored in the target program arguments.
-    pub fn get_local(&self) -> SocketAddrV4 {
+    pub fn get_local(&self) -> SocketAddr {
         self.local
     }
 
     /// Returns the remote endpoint address parameter stored in the target program arguments.
-    pub fn get_remote(&self) -> SocketAddrV4 {
+    pub fn get_remote(&self) -> SocketAddr {
         self.remote
     }
 
@@ -153,15 +222,25 @@ impl ProgramArguments {
         self.injection_rate
     }
 
+    /// Returns the transport parameter stored in the target program arguments.
+    pub fn get_transport(&self) -> TransportMode {
+        self.transport
+    }
+
+    /// Returns the multicast group parameter stored in the target program arguments, if any.
+    pub fn get_multicast(&self) -> Option<Ipv4Addr> {
+        self.multicast
+    }
+
     /// Sets the local address and port number parameters in the target program arguments.
     fn set_local_addr(&mut self, addr: &str) -> Result<()> {
-        self.local = SocketAddrV4::from_str(addr)?;
+        self.local = SocketAddr::from_str(addr)?;
         Ok(())
     }
 
     /// Sets the remote address and port number parameters in the target program arguments.
     fn set_remote_addr(&mut self, addr: &str) -> Result<()> {
-        self.remote = SocketAddrV4::from_str(addr)?;
+        self.remote = SocketAddr::from_str(addr)?;
         Ok(())
     }
 
@@ -199,11 +278,15 @@ struct Application {
     // Local socket descriptor.
     sockqd: QDesc,
     /// Remote endpoint.
-    remote: SocketAddrV4,
+    remote: SocketAddr,
     /// Buffer size.
     bufsize: usize,
     /// Injection rate
     injection_rate: u64,
+    /// Transport generating this traffic.
+    transport: TransportMode,
+    /// Destination to flood traffic to: the joined multicast group, if any, else `remote`.
+    destination: SocketAddr,
 }
 
 /// Associated Functions for the Application
@@ -214,13 +297,25 @@ impl Application {
     /// Instantiates the application.
     pub fn new(mut libos: LibOS, args: &ProgramArguments) -> Self {
         // Extract arguments.
-        let local: SocketAddrV4 = args.get_local();
-        let remote: SocketAddrV4 = args.get_remote();
+        let local: SocketAddr = args.get_local();
+        let remote: SocketAddr = args.get_remote();
         let bufsize: usize = args.get_bufsize();
         let injection_rate: u64 = args.get_injection_rate();
-
-        // Create UDP socket.
-        let sockqd: QDesc = match libos.socket(libc::AF_INET, libc::SOCK_DGRAM, 1) {
+        let transport: TransportMode = args.get_transport();
+
+        // Create the socket. A QUIC run still allocates a `SOCK_DGRAM` socket (QUIC is
+        // multiplexed onto the UDP datapath), but passes `IPPROTO_QUIC` so `InetStack::socket`
+        // hands it to the QUIC peer instead of the plain UDP one. The address family is driven by
+        // `--local`, matching the family `--remote` (and any `--multicast` group) must share.
+        let domain: i32 = match local {
+            SocketAddr::V4(_) => libc::AF_INET,
+            SocketAddr::V6(_) => AF_INET6,
+        };
+        let protocol: i32 = match transport {
+            TransportMode::Udp => 1,
+            TransportMode::Quic => IPPROTO_QUIC,
+        };
+        let sockqd: QDesc = match libos.socket(domain, libc::SOCK_DGRAM, protocol) {
             Ok(qd) => qd,
             Err(e) => panic!("failed to create socket: {:?}", e.cause),
         };
@@ -231,8 +326,45 @@ impl Application {
             Err(e) => panic!("failed to bind socket: {:?}", e.cause),
         };
 
+        // Join the multicast group, if requested, and (with the UDP transport) flood to it instead
+        // of to `remote`; a QUIC run still pushes onto its stream to `remote`.
+        //
+        // Multicast membership is IPv4-only (`join_multicast` takes an `Ipv4Addr`), so `--local`
+        // must also be IPv4 whenever `--multicast` is given.
+        let destination: SocketAddr = match args.get_multicast() {
+            Some(group) => {
+                let local_ipv4: Ipv4Addr = match local {
+                    SocketAddr::V4(addr) => *addr.ip(),
+                    SocketAddr::V6(_) => panic!("--multicast requires an IPv4 --local address"),
+                };
+                match libos.join_multicast(sockqd, group, local_ipv4) {
+                    Ok(()) => (),
+                    Err(e) => panic!("failed to join multicast group: {:?}", e.cause),
+                };
+                SocketAddr::V4(SocketAddrV4::new(group, remote.port()))
+            },
+            None => remote,
+        };
+
+        // For QUIC, drive the handshake to completion before generating stream traffic.
+        if transport == TransportMode::Quic {
+            let qt: QToken = match libos.connect(sockqd, remote) {
+                Ok(qt) => qt,
+                Err(e) => panic!("failed to start QUIC handshake: {:?}", e.cause),
+            };
+            match libos.wait(qt) {
+                Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_CONNECT => (),
+                Ok(qr) => panic!("unexpected result (qr_opcode={:?})", qr.qr_opcode),
+                Err(e) => panic!("QUIC handshake failed: {:?}", e.cause),
+            };
+        }
+
         println!("Local Address:  {:?}", local);
         println!("Remote Address: {:?}", remote);
+        println!("Transport:      {:?}", transport);
+        if let Some(group) = args.get_multicast() {
+            println!("Multicast Group: {:?}", group);
+        }
 
         Self {
             libos,
@@ -240,6 +372,8 @@ impl Application {
             remote,
             bufsize,
             injection_rate,
+            transport,
+            destination,
         }
     }
 
@@ -262,9 +396,17 @@ impl Application {
             if last_push.elapsed() > Duration::from_nanos(self.injection_rate) {
                 let sga: demi_sgarray_t = self.mksga(self.bufsize, 0x65);
 
-                let qt: QToken = match self.libos.pushto(self.sockqd, &sga, self.remote) {
-                    Ok(qt) => qt,
-                    Err(e) => panic!("failed to push data to socket: {:?}", e.cause),
+                // A QUIC run is already connected, so it pushes onto the stream directly; a plain
+                // UDP run has no notion of a peer and must address every datagram explicitly.
+                let qt: QToken = match self.transport {
+                    TransportMode::Udp => match self.libos.pushto(self.sockqd, &sga, self.destination) {
+                        Ok(qt) => qt,
+                        Err(e) => panic!("failed to push data to socket: {:?}", e.cause),
+                    },
+                    TransportMode::Quic => match self.libos.push(self.sockqd, &sga) {
+                        Ok(qt) => qt,
+                        Err(e) => panic!("failed to push data to stream: {:?}", e.cause),
+                    },
                 };
                 match self.libos.wait(qt) {
                     Ok(qr) if qr.qr_opcode == demi_opcode_t::DEMI_OPC_PUSH => (),
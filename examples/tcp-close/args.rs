@@ -10,47 +10,176 @@ use clap::{
     Arg,
     ArgMatches,
     Command,
+    ValueEnum,
 };
 use std::{
-    net::SocketAddrV4,
+    collections::HashMap,
+    env,
+    fs,
+    net::{
+        Ipv4Addr,
+        SocketAddr,
+        SocketAddrV4,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
     str::FromStr,
 };
 
 //======================================================================================================================
-// Program Arguments
+// Structures
 //======================================================================================================================
 
-/// Program Arguments
+/// Run mode for a benchmark peer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RunMode {
+    /// Runs as a single, self-contained peer.
+    Standalone,
+    /// Drives clients one after another.
+    Sequential,
+    /// Drives clients concurrently.
+    Concurrent,
+}
+
+/// Peer type for a benchmark run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PeerType {
+    /// Accepts incoming connections.
+    Server,
+    /// Initiates outgoing connections.
+    Client,
+}
+
+/// Configuration for exporting benchmark results over Prometheus.
+///
+/// This is modeled as a self-contained param group: a [`PeerSpec`] only embeds it when the
+/// `--metrics-addr` flag is passed, so peers that do not opt in pay no cost for the subsystem.
+#[derive(Clone, Copy, Debug)]
+pub struct MetricsConfig {
+    /// Address on which to expose the Prometheus scrape endpoint.
+    addr: SocketAddrV4,
+    /// Whether the endpoint binds on all interfaces, rather than just localhost.
+    external: bool,
+}
+
+impl MetricsConfig {
+    /// Returns the address on which the scrape endpoint is exposed, substituting the unspecified
+    /// address for the configured host when `--metrics-external` was passed.
+    pub fn addr(&self) -> SocketAddrV4 {
+        if self.external {
+            SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, self.addr.port())
+        } else {
+            self.addr
+        }
+    }
+
+    /// Returns whether the scrape endpoint binds on all interfaces.
+    pub fn external(&self) -> bool {
+        self.external
+    }
+}
+
+/// Layered configuration file, holding a table of known hosts addressable by nickname.
+#[derive(Debug, Default, serde::Deserialize)]
+struct ConfigFile {
+    /// Table of nicknames to socket addresses.
+    #[serde(default)]
+    hosts: HashMap<String, SocketAddrV4>,
+}
+
+/// Arguments describing a single peer within a (possibly multi-peer) invocation.
 #[derive(Debug)]
-pub struct ProgramArguments {
+pub struct PeerSpec {
     /// Run mode.
-    run_mode: String,
-    /// Socket IPv4 address.
-    addr: SocketAddrV4,
+    run_mode: RunMode,
+    /// Socket address, either IPv4 or (bracketed) IPv6.
+    addr: SocketAddr,
     /// Number of clients
     nclients: Option<usize>,
     /// Peer type.
-    peer_type: Option<String>,
+    peer_type: Option<PeerType>,
+    /// Path to the configuration file that was loaded (or would have been, had it existed), if any.
+    config_path: Option<PathBuf>,
+    /// Metrics-export configuration, if enabled.
+    metrics_config: Option<MetricsConfig>,
 }
 
+/// Program Arguments
+///
+/// Parses the command line into one [`PeerSpec`] per `--peer` group, so a single invocation can
+/// describe a mixed topology (e.g. a server and several differently-configured clients) instead of
+/// only `nclients` identical peers.
+#[derive(Debug)]
+pub struct ProgramArguments;
+
 impl ProgramArguments {
+    /// Name of the server configuration file, relative to the platform config directory.
+    const SERVER_CONFIG_FILE: &'static str = "server.toml";
+    /// Name of the client configuration file, relative to the platform config directory.
+    const CLIENT_CONFIG_FILE: &'static str = "client.toml";
+
     /// Parses the program arguments from the command line interface.
-    pub fn new(app_name: &'static str, app_author: &'static str, app_about: &'static str) -> Result<Self> {
+    ///
+    /// Clap cannot natively scope repeated flags to a group, so the raw process arguments are
+    /// split into segments on `--peer` and each segment is fed to the per-peer parser below.
+    pub fn new(app_name: &'static str, app_author: &'static str, app_about: &'static str) -> Result<Vec<PeerSpec>> {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let groups: Vec<Vec<String>> = Self::split_peer_groups(args);
+        if groups.is_empty() {
+            anyhow::bail!("missing peer group (expected at least one --peer)");
+        }
+
+        groups
+            .into_iter()
+            .map(|group| Self::parse_peer_group(app_name, app_author, app_about, group))
+            .collect()
+    }
+
+    /// Splits `args` into groups, each starting at a `--peer` token and running up to (but not
+    /// including) the next one. Tokens before the first `--peer` form a leading group of their own.
+    fn split_peer_groups(args: Vec<String>) -> Vec<Vec<String>> {
+        let mut groups: Vec<Vec<String>> = Vec::new();
+        let mut current: Vec<String> = Vec::new();
+
+        for arg in args {
+            if arg == "--peer" && !current.is_empty() {
+                groups.push(current);
+                current = Vec::new();
+            }
+            current.push(arg);
+        }
+        if !current.is_empty() {
+            groups.push(current);
+        }
+
+        groups
+    }
+
+    /// Parses a single `--peer` group into a [`PeerSpec`].
+    fn parse_peer_group(
+        app_name: &'static str,
+        app_author: &'static str,
+        app_about: &'static str,
+        group: Vec<String>,
+    ) -> Result<PeerSpec> {
         let matches: ArgMatches = Command::new(app_name)
             .author(app_author)
             .about(app_about)
+            .no_binary_name(true)
             .arg(
                 Arg::new("addr")
                     .long("address")
                     .value_parser(clap::value_parser!(String))
                     .required(true)
-                    .value_name("ADDRESS:PORT")
-                    .help("Sets socket address"),
+                    .value_name("ADDRESS:PORT|[IPV6]:PORT|NICKNAME")
+                    .help("Sets socket address (IPv4, bracketed IPv6, or by nickname from the config file)"),
             )
             .arg(
                 Arg::new("peer")
                     .long("peer")
-                    .value_parser(clap::value_parser!(String))
+                    .value_parser(clap::value_parser!(PeerType))
                     .required(false)
                     .value_name("server|client")
                     .help("Sets peer type"),
@@ -66,30 +195,79 @@ impl ProgramArguments {
             .arg(
                 Arg::new("run-mode")
                     .long("run-mode")
-                    .value_parser(clap::value_parser!(String))
+                    .value_parser(clap::value_parser!(RunMode))
                     .required(true)
                     .value_name("standalone|sequential|concurrent")
                     .help("Sets run mode"),
             )
-            .get_matches();
+            .arg(
+                Arg::new("config")
+                    .long("config")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .required(false)
+                    .value_name("PATH")
+                    .help("Overrides the default config file path"),
+            )
+            .arg(
+                Arg::new("metrics-addr")
+                    .long("metrics-addr")
+                    .value_parser(clap::value_parser!(String))
+                    .required(false)
+                    .value_name("ADDRESS:PORT")
+                    .help("Enables Prometheus metrics export on the given address"),
+            )
+            .arg(
+                Arg::new("metrics-external")
+                    .long("metrics-external")
+                    .action(clap::ArgAction::SetTrue)
+                    .requires("metrics-addr")
+                    .help("Binds the metrics endpoint on all interfaces instead of localhost"),
+            )
+            .get_matches_from(group);
 
         // Run mode.
-        let run_mode: String = matches
-            .get_one::<String>("run-mode")
-            .ok_or(anyhow::anyhow!("missing run mode"))?
-            .to_string();
-
-        // Socket address.
-        let addr: SocketAddrV4 = {
-            let addr: &String = matches.get_one::<String>("addr").expect("missing address");
-            SocketAddrV4::from_str(addr)?
+        let run_mode: RunMode = *matches
+            .get_one::<RunMode>("run-mode")
+            .ok_or(anyhow::anyhow!("missing run mode"))?;
+
+        // Peer type (parsed ahead of the address so we know which config file to consult).
+        let peer_type: Option<PeerType> = matches.get_one::<PeerType>("peer").copied();
+
+        // Resolve the config file path and load it, if present.
+        let config_path: Option<PathBuf> = match matches.get_one::<PathBuf>("config") {
+            Some(path) => Some(path.clone()),
+            None => Self::default_config_path(peer_type),
+        };
+        let config: ConfigFile = match &config_path {
+            Some(path) => Self::load_config_file(path)?,
+            None => ConfigFile::default(),
+        };
+
+        // Socket address: either a literal `ADDRESS:PORT`/`[IPV6]:PORT` or a nickname resolved
+        // against the config file, with the CLI value always taking precedence over anything the
+        // config file implies.
+        let addr: SocketAddr = {
+            let raw: &String = matches.get_one::<String>("addr").expect("missing address");
+            Self::resolve_addr(raw, &config)?
         };
 
-        let mut args: ProgramArguments = Self {
+        // Metrics-export configuration.
+        let metrics_config: Option<MetricsConfig> = match matches.get_one::<String>("metrics-addr") {
+            Some(addr) => {
+                let addr: SocketAddrV4 = SocketAddrV4::from_str(addr)?;
+                let external: bool = matches.get_flag("metrics-external");
+                Some(MetricsConfig { addr, external })
+            },
+            None => None,
+        };
+
+        let mut spec: PeerSpec = PeerSpec {
             run_mode,
             addr,
             nclients: None,
-            peer_type: None,
+            peer_type,
+            config_path,
+            metrics_config,
         };
 
         // Number of clients.
@@ -97,22 +275,49 @@ impl ProgramArguments {
             if *nclients == 0 {
                 anyhow::bail!("invalid nclients");
             }
-            args.nclients = Some(*nclients);
+            spec.nclients = Some(*nclients);
         }
 
-        // Peer type.
-        if let Some(peer_type) = matches.get_one::<String>("peer") {
-            if peer_type != "server" && peer_type != "client" {
-                anyhow::bail!("invalid peer type");
-            }
-            args.peer_type = Some(peer_type.to_string());
+        Ok(spec)
+    }
+
+    /// Resolves `raw` to a socket address, either parsing it directly (accepting both IPv4 and
+    /// bracketed IPv6 literals) or looking it up by nickname in `config`.
+    fn resolve_addr(raw: &str, config: &ConfigFile) -> Result<SocketAddr> {
+        match SocketAddr::from_str(raw) {
+            Ok(addr) => Ok(addr),
+            Err(_) => config
+                .hosts
+                .get(raw)
+                .copied()
+                .map(SocketAddr::V4)
+                .ok_or(anyhow::anyhow!("unknown host nickname: {}", raw)),
         }
+    }
 
-        Ok(args)
+    /// Loads and parses a config file from `path`. A missing file is treated as an empty configuration.
+    fn load_config_file(path: &Path) -> Result<ConfigFile> {
+        if !path.exists() {
+            return Ok(ConfigFile::default());
+        }
+        let contents: String = fs::read_to_string(path)?;
+        let config: ConfigFile = toml::from_str(&contents)?;
+        Ok(config)
+    }
+
+    /// Returns the default config file path for `peer_type`, rooted at the platform config directory.
+    fn default_config_path(peer_type: Option<PeerType>) -> Option<PathBuf> {
+        let filename: &str = match peer_type {
+            Some(PeerType::Client) => Self::CLIENT_CONFIG_FILE,
+            _ => Self::SERVER_CONFIG_FILE,
+        };
+        dirs::config_dir().map(|dir| dir.join(app_config_subdir()).join(filename))
     }
+}
 
+impl PeerSpec {
     /// Returns the `addr` command line argument.
-    pub fn addr(&self) -> SocketAddrV4 {
+    pub fn addr(&self) -> SocketAddr {
         self.addr
     }
 
@@ -122,12 +327,129 @@ impl ProgramArguments {
     }
 
     /// Returns the `peer_type` command line argument.
-    pub fn peer_type(&self) -> Option<String> {
-        self.peer_type.clone()
+    pub fn peer_type(&self) -> Option<PeerType> {
+        self.peer_type
     }
 
     /// Returns the `run_mode` command line argument.
-    pub fn run_mode(&self) -> String {
-        self.run_mode.clone()
+    pub fn run_mode(&self) -> RunMode {
+        self.run_mode
+    }
+
+    /// Returns the config file path that was loaded (or would have been loaded), if any.
+    pub fn config_path(&self) -> Option<PathBuf> {
+        self.config_path.clone()
+    }
+
+    /// Returns the metrics-export configuration, if metrics export was enabled.
+    pub fn metrics_config(&self) -> Option<MetricsConfig> {
+        self.metrics_config
+    }
+}
+
+/// Name of the subdirectory of the platform config directory that holds demikernel example configs.
+fn app_config_subdir() -> &'static str {
+    "demikernel"
+}
+
+//======================================================================================================================
+// Unit Tests
+//======================================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `resolve_addr` should parse a literal IPv4 address without consulting the config file.
+    #[test]
+    fn resolve_addr_literal_ipv4() {
+        let config: ConfigFile = ConfigFile::default();
+        let addr: SocketAddr = ProgramArguments::resolve_addr("127.0.0.1:8080", &config).unwrap();
+        assert_eq!(addr, SocketAddr::from_str("127.0.0.1:8080").unwrap());
+    }
+
+    /// `resolve_addr` should parse a bracketed IPv6 literal without consulting the config file.
+    #[test]
+    fn resolve_addr_literal_ipv6() {
+        let config: ConfigFile = ConfigFile::default();
+        let addr: SocketAddr = ProgramArguments::resolve_addr("[::1]:8080", &config).unwrap();
+        assert_eq!(addr, SocketAddr::from_str("[::1]:8080").unwrap());
+    }
+
+    /// `resolve_addr` should fall back to a config-file nickname lookup when `raw` does not parse
+    /// as a literal address.
+    #[test]
+    fn resolve_addr_nickname_hit() {
+        let mut config: ConfigFile = ConfigFile::default();
+        config
+            .hosts
+            .insert("server1".to_string(), SocketAddrV4::from_str("10.0.0.1:9000").unwrap());
+        let addr: SocketAddr = ProgramArguments::resolve_addr("server1", &config).unwrap();
+        assert_eq!(addr, SocketAddr::V4(SocketAddrV4::from_str("10.0.0.1:9000").unwrap()));
+    }
+
+    /// An unknown nickname that also fails to parse as a literal address should be rejected.
+    #[test]
+    fn resolve_addr_unknown_nickname() {
+        let config: ConfigFile = ConfigFile::default();
+        assert!(ProgramArguments::resolve_addr("not-a-host", &config).is_err());
+    }
+
+    /// A single `--peer` group with no other tokens before it.
+    #[test]
+    fn split_peer_groups_single() {
+        let args: Vec<String> = vec!["--peer", "--address", "127.0.0.1:8080"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let groups: Vec<Vec<String>> = ProgramArguments::split_peer_groups(args.clone());
+        assert_eq!(groups, vec![args]);
+    }
+
+    /// Two `--peer` groups should each retain their own tokens, split at the second `--peer`.
+    #[test]
+    fn split_peer_groups_multiple() {
+        let args: Vec<String> = vec![
+            "--peer",
+            "--address",
+            "127.0.0.1:8080",
+            "--peer",
+            "--address",
+            "127.0.0.1:9090",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        let groups: Vec<Vec<String>> = ProgramArguments::split_peer_groups(args);
+        assert_eq!(
+            groups,
+            vec![
+                vec!["--peer".to_string(), "--address".to_string(), "127.0.0.1:8080".to_string()],
+                vec!["--peer".to_string(), "--address".to_string(), "127.0.0.1:9090".to_string()],
+            ]
+        );
+    }
+
+    /// No arguments at all should yield no groups (the caller turns this into a "missing peer
+    /// group" error).
+    #[test]
+    fn split_peer_groups_no_args() {
+        let groups: Vec<Vec<String>> = ProgramArguments::split_peer_groups(Vec::new());
+        assert!(groups.is_empty());
+    }
+
+    /// Tokens preceding the first `--peer` form their own (leading) group instead of being merged
+    /// into the `--peer` group that follows.
+    #[test]
+    fn split_peer_groups_leading_tokens() {
+        let args: Vec<String> = vec!["--address".to_string(), "127.0.0.1:8080".to_string(), "--peer".to_string()];
+        let groups: Vec<Vec<String>> = ProgramArguments::split_peer_groups(args);
+        assert_eq!(
+            groups,
+            vec![
+                vec!["--address".to_string(), "127.0.0.1:8080".to_string()],
+                vec!["--peer".to_string()],
+            ]
+        );
     }
 }
@@ -5,6 +5,10 @@
 // Imports
 //======================================================================================================================
 
+use crate::{
+    args::MetricsConfig,
+    metrics::Metrics,
+};
 use anyhow::Result;
 use demikernel::{
     runtime::types::{
@@ -17,7 +21,9 @@ use demikernel::{
 };
 use std::{
     collections::HashMap,
-    net::SocketAddrV4,
+    net::SocketAddr,
+    sync::Arc,
+    time::Instant,
 };
 
 //======================================================================================================================
@@ -27,12 +33,18 @@ use std::{
 #[cfg(target_os = "windows")]
 pub const AF_INET: i32 = windows::Win32::Networking::WinSock::AF_INET.0 as i32;
 
+#[cfg(target_os = "windows")]
+pub const AF_INET6: i32 = windows::Win32::Networking::WinSock::AF_INET6.0 as i32;
+
 #[cfg(target_os = "windows")]
 pub const SOCK_STREAM: i32 = windows::Win32::Networking::WinSock::SOCK_STREAM as i32;
 
 #[cfg(target_os = "linux")]
 pub const AF_INET: i32 = libc::AF_INET;
 
+#[cfg(target_os = "linux")]
+pub const AF_INET6: i32 = libc::AF_INET6;
+
 #[cfg(target_os = "linux")]
 pub const SOCK_STREAM: i32 = libc::SOCK_STREAM;
 
@@ -45,11 +57,14 @@ pub struct TcpClient {
     /// Underlying libOS.
     libos: LibOS,
     /// Address of remote peer.
-    remote: SocketAddrV4,
+    remote: SocketAddr,
     /// Number of clients that established a connection.
     clients_connected: usize,
     /// Number of clients that closed their connection.
     clients_closed: usize,
+    /// Latency/throughput counters and histograms for this run, published over Prometheus when
+    /// `metrics_config` was passed to [`new`](Self::new).
+    metrics: Arc<Metrics>,
 }
 
 //======================================================================================================================
@@ -57,25 +72,43 @@ pub struct TcpClient {
 //======================================================================================================================
 
 impl TcpClient {
-    /// Creates a new TCP client.
-    pub fn new(libos: LibOS, remote: SocketAddrV4) -> Result<Self> {
+    /// Creates a new TCP client. If `metrics_config` is `Some`, starts the Prometheus scrape
+    /// endpoint it describes; recording into `metrics` otherwise happens unconditionally (and
+    /// effectively for free), so turning the endpoint on never changes the numbers, only whether
+    /// they are exposed.
+    pub fn new(libos: LibOS, remote: SocketAddr, metrics_config: Option<MetricsConfig>) -> Result<Self> {
         println!("Connecting to: {:?}", remote);
+        let metrics: Arc<Metrics> = Metrics::new();
+        if let Some(config) = metrics_config {
+            crate::metrics::serve(&config, metrics.clone())?;
+            println!("Exporting metrics on: {:?}", config.addr());
+        }
         Ok(Self {
             libos,
             remote,
             clients_connected: 0,
             clients_closed: 0,
+            metrics,
         })
     }
 
+    /// Returns the address family to create sockets with, matching `self.remote`.
+    fn domain(&self) -> i32 {
+        match self.remote {
+            SocketAddr::V4(_) => AF_INET,
+            SocketAddr::V6(_) => AF_INET6,
+        }
+    }
+
     /// Attempts to close several connections sequentially.
     pub fn run_sequential(&mut self, nclients: usize) -> Result<()> {
         // Open several connections.
         for i in 0..nclients {
             // Create TCP socket.
-            let sockqd: QDesc = self.libos.socket(AF_INET, SOCK_STREAM, 0)?;
+            let sockqd: QDesc = self.libos.socket(self.domain(), SOCK_STREAM, 0)?;
 
             // Connect TCP socket.
+            let connect_start: Instant = Instant::now();
             let qt: QToken = self.libos.connect(sockqd, self.remote)?;
 
             // Wait for connection to be established.
@@ -84,6 +117,8 @@ impl TcpClient {
             // Parse result.
             match qr.qr_opcode {
                 demi_opcode_t::DEMI_OPC_CONNECT => {
+                    self.metrics.connect_latency.observe(connect_start.elapsed());
+                    self.metrics.connects_total.inc();
                     println!("{} clients connected", i + 1);
                 },
                 demi_opcode_t::DEMI_OPC_FAILED => panic!("operation failed (qr_ret={:?})", qr.qr_ret),
@@ -92,6 +127,7 @@ impl TcpClient {
 
             // Close TCP socket.
             self.libos.close(sockqd)?;
+            self.metrics.closes_total.inc();
         }
 
         Ok(())
@@ -102,16 +138,18 @@ impl TcpClient {
         let mut qds: Vec<QDesc> = Vec::default();
         let mut qts: Vec<QToken> = Vec::default();
         let mut qts_reverse: HashMap<QToken, QDesc> = HashMap::default();
+        let mut connect_starts: HashMap<QToken, Instant> = HashMap::default();
 
         // Open several connections.
         for _ in 0..nclients {
             // Create TCP socket.
-            let qd: QDesc = self.libos.socket(AF_INET, SOCK_STREAM, 0)?;
+            let qd: QDesc = self.libos.socket(self.domain(), SOCK_STREAM, 0)?;
             qds.push(qd);
 
             // Connect TCP socket.
             let qt: QToken = self.libos.connect(qd, self.remote)?;
             qts_reverse.insert(qt, qd);
+            connect_starts.insert(qt, Instant::now());
             qts.push(qt);
         }
 
@@ -128,6 +166,9 @@ impl TcpClient {
                 qts_reverse
                     .remove(&qt)
                     .ok_or(anyhow::anyhow!("unregistered queue token"))?;
+                if let Some(connect_start) = connect_starts.remove(&qt) {
+                    self.metrics.connect_latency.observe(connect_start.elapsed());
+                }
                 qr
             };
 
@@ -137,10 +178,12 @@ impl TcpClient {
                     let qd: QDesc = qr.qr_qd.into();
 
                     self.clients_connected += 1;
+                    self.metrics.connects_total.inc();
                     println!("{} clients connected", self.clients_connected);
 
                     // Close TCP socket.
                     self.clients_closed += 1;
+                    self.metrics.closes_total.inc();
                     self.libos.close(qd)?;
                 },
                 demi_opcode_t::DEMI_OPC_FAILED => panic!("operation failed (qr_ret={:?})", qr.qr_ret),
@@ -0,0 +1,176 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//======================================================================================================================
+// Imports
+//======================================================================================================================
+
+use crate::args::MetricsConfig;
+use anyhow::Result;
+use std::{
+    io::Write,
+    net::TcpListener,
+    sync::{
+        atomic::{
+            AtomicU64,
+            Ordering,
+        },
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+//======================================================================================================================
+// Constants
+//======================================================================================================================
+
+/// Upper bound (in microseconds) of each latency bucket, Prometheus histogram style (each bucket
+/// counts every observation less than or equal to its bound).
+const LATENCY_BUCKETS_US: &[u64] = &[100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000];
+
+//======================================================================================================================
+// Structures
+//======================================================================================================================
+
+/// Monotonically increasing named counter, exported as a Prometheus `counter`.
+#[derive(Default)]
+pub struct Counter {
+    value: AtomicU64,
+}
+
+impl Counter {
+    /// Increments the counter by one.
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// Fixed-bucket latency histogram, exported as a Prometheus `histogram`.
+///
+/// Buckets are bounded in microseconds rather than `f64` seconds: every caller records a
+/// [`Duration`], so bucketing on integer microsecond boundaries avoids floating-point comparisons
+/// on the hot path.
+#[derive(Default)]
+pub struct Histogram {
+    /// Per-bucket cumulative counts, parallel to `LATENCY_BUCKETS_US` plus a final `+Inf` bucket.
+    buckets: Vec<AtomicU64>,
+    sum_us: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: (0..=LATENCY_BUCKETS_US.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum_us: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Records one observation of `latency`.
+    pub fn observe(&self, latency: Duration) {
+        let us: u64 = latency.as_micros() as u64;
+        let bucket: usize = LATENCY_BUCKETS_US
+            .iter()
+            .position(|&bound| us <= bound)
+            .unwrap_or(LATENCY_BUCKETS_US.len());
+        // Prometheus histogram buckets are cumulative: every bucket at or above the matching one
+        // also counts this observation.
+        for b in &self.buckets[bucket..] {
+            b.fetch_add(1, Ordering::Relaxed);
+        }
+        self.sum_us.fetch_add(us, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Per-run latency/throughput metrics, published over Prometheus when `--metrics-addr` is passed.
+///
+/// Recording into these counters/histograms is unconditional and effectively free (a handful of
+/// relaxed atomic operations); only the scrape endpoint itself is gated behind `MetricsConfig`, so
+/// enabling `--metrics-addr` never changes anything but whether the numbers are exposed.
+#[derive(Default)]
+pub struct Metrics {
+    /// Total number of connections established.
+    pub connects_total: Counter,
+    /// Total number of connections closed.
+    pub closes_total: Counter,
+    /// Latency of a single connect-to-established round trip.
+    pub connect_latency: Histogram,
+}
+
+impl Metrics {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            connects_total: Counter::default(),
+            closes_total: Counter::default(),
+            connect_latency: Histogram::new(),
+        })
+    }
+
+    /// Renders every metric in Prometheus text exposition format.
+    fn render(&self) -> String {
+        let mut out: String = String::new();
+        out.push_str("# TYPE tcp_close_connects_total counter\n");
+        out.push_str(&format!("tcp_close_connects_total {}\n", self.connects_total.get()));
+        out.push_str("# TYPE tcp_close_closes_total counter\n");
+        out.push_str(&format!("tcp_close_closes_total {}\n", self.closes_total.get()));
+
+        out.push_str("# TYPE tcp_close_connect_latency_microseconds histogram\n");
+        let mut cumulative_le: Vec<(String, &AtomicU64)> = LATENCY_BUCKETS_US
+            .iter()
+            .map(|bound| bound.to_string())
+            .chain(std::iter::once("+Inf".to_string()))
+            .zip(self.connect_latency.buckets.iter())
+            .collect();
+        for (bound, count) in cumulative_le.drain(..) {
+            out.push_str(&format!(
+                "tcp_close_connect_latency_microseconds_bucket{{le=\"{}\"}} {}\n",
+                bound,
+                count.load(Ordering::Relaxed)
+            ));
+        }
+        out.push_str(&format!(
+            "tcp_close_connect_latency_microseconds_sum {}\n",
+            self.connect_latency.sum_us.load(Ordering::Relaxed)
+        ));
+        out.push_str(&format!(
+            "tcp_close_connect_latency_microseconds_count {}\n",
+            self.connect_latency.count.load(Ordering::Relaxed)
+        ));
+
+        out
+    }
+}
+
+/// Spawns a background thread serving `GET /metrics` in Prometheus text exposition format off
+/// `config`'s address, for as long as the calling process is alive.
+///
+/// Runs on a bare `TcpListener` rather than pulling in an HTTP server crate: the scrape endpoint
+/// only ever needs to answer one fixed, unauthenticated `GET /metrics` request.
+pub fn serve(config: &MetricsConfig, metrics: Arc<Metrics>) -> Result<()> {
+    let listener: TcpListener = TcpListener::bind(config.addr())?;
+    thread::Builder::new()
+        .name("tcp-close-metrics".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let body: String = metrics.render();
+                let response: String = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        })?;
+    Ok(())
+}
@@ -3,12 +3,14 @@ use crate::{
     rand::Seed,
 };
 use base64::{decode_config_slice, STANDARD_NO_PAD};
-use std::net::Ipv4Addr;
+use std::net::{Ipv4Addr, Ipv6Addr};
 
 #[derive(Clone)]
 pub struct Options {
     pub my_link_addr: MacAddress,
     pub my_ipv4_addr: Ipv4Addr,
+    /// Local IPv6 address, if this peer is dual-stack. `None` keeps the IPv4-only datapath.
+    pub my_ipv6_addr: Option<Ipv6Addr>,
     pub arp: arp::Options,
     pub rng_seed: Option<String>,
 }
@@ -14,11 +14,17 @@ use crate::{
                 EtherType2,
                 Ethernet2Header,
             },
+            ipv6::Ipv6Peer,
+            quic::QuicPeer,
             queue::InetQueue,
             tcp::operations::ConnectFuture,
             udp::UdpOperation,
             Peer,
         },
+        trace::{
+            Trace,
+            TraceMode,
+        },
     },
     pal::constants::{
         AF_INET_VALUE,
@@ -57,12 +63,18 @@ use ::libc::c_int;
 use ::std::{
     any::Any,
     cell::RefCell,
+    mem::MaybeUninit,
     net::{
         Ipv4Addr,
+        Ipv6Addr,
+        SocketAddr,
         SocketAddrV4,
     },
     rc::Rc,
-    time::Instant,
+    time::{
+        Duration,
+        Instant,
+    },
 };
 
 #[cfg(feature = "profiler")]
@@ -79,6 +91,7 @@ pub mod collections;
 pub mod futures;
 pub mod options;
 pub mod protocols;
+pub mod trace;
 
 //==============================================================================
 // Constants
@@ -87,15 +100,58 @@ pub mod protocols;
 const TIMER_RESOLUTION: usize = 64;
 const MAX_RECV_ITERS: usize = 2;
 
+/// Lower bound of [`IdleBackoff`]'s sleep, once a `wait*` loop has found nothing to do.
+const MIN_IDLE_BACKOFF: Duration = Duration::from_micros(10);
+/// Upper bound of [`IdleBackoff`]'s sleep: even a fully idle wait re-checks for a completion at
+/// least this often.
+const MAX_IDLE_BACKOFF: Duration = Duration::from_millis(1);
+
+/// Protocol number for QUIC-over-UDP sockets, passed as the `protocol` argument to
+/// [`InetStack::socket`] alongside `AF_INET`/`SOCK_DGRAM` to request a QUIC endpoint instead of a
+/// plain UDP one.
+const IPPROTO_QUIC: c_int = 261;
+
+/// Bounded exponential backoff for the `wait*` spin loops, so an idle wait doesn't pin a core at
+/// 100% CPU re-polling as fast as possible. Starts at no delay (so the first, most-likely-to-matter
+/// re-poll is immediate) and grows towards [`MAX_IDLE_BACKOFF`], which bounds how late a completion
+/// that actually occurs can be noticed.
+struct IdleBackoff {
+    delay: Duration,
+}
+
+impl IdleBackoff {
+    fn new() -> Self {
+        Self { delay: Duration::ZERO }
+    }
+
+    /// Sleeps for the current backoff delay (a no-op the first time), then grows it.
+    fn idle(&mut self) {
+        if !self.delay.is_zero() {
+            ::std::thread::sleep(self.delay);
+        }
+        self.delay = (self.delay * 2).clamp(MIN_IDLE_BACKOFF, MAX_IDLE_BACKOFF);
+    }
+}
+
+/// Returns whether `deadline` (if any) has passed as of `now`.
+fn deadline_exceeded(deadline: Option<Instant>, now: Instant) -> bool {
+    matches!(deadline, Some(deadline) if now >= deadline)
+}
+
 pub struct InetStack {
     arp: ArpPeer,
     ipv4: Peer,
+    quic: QuicPeer,
+    /// Dual-stack companion to `ipv4`, present whenever the stack was configured with an IPv6 address.
+    ipv6: Option<Ipv6Peer>,
     qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
     rt: Rc<dyn NetworkRuntime>,
     local_link_addr: MacAddress,
     scheduler: Scheduler,
     clock: TimerRc,
     ts_iters: usize,
+    /// Record-and-replay state, present whenever the stack was configured with a `trace_mode`.
+    trace: Option<Trace>,
 }
 
 impl InetStack {
@@ -109,7 +165,10 @@ impl InetStack {
         tcp_config: TcpConfig,
         rng_seed: [u8; 32],
         arp_config: ArpConfig,
+        local_ipv6_addr: Option<Ipv6Addr>,
+        trace_mode: Option<TraceMode>,
     ) -> Result<Self, Fail> {
+        let trace: Option<Trace> = trace_mode.as_ref().map(Trace::new).transpose()?;
         let qtable: Rc<RefCell<IoQueueTable<InetQueue>>> = Rc::new(RefCell::new(IoQueueTable::<InetQueue>::new()));
         let arp: ArpPeer = ArpPeer::new(
             rt.clone(),
@@ -126,20 +185,41 @@ impl InetStack {
             clock.clone(),
             local_link_addr,
             local_ipv4_addr,
-            udp_config,
-            tcp_config,
+            udp_config.clone(),
+            tcp_config.clone(),
             arp.clone(),
             rng_seed,
         )?;
+        let quic: QuicPeer = QuicPeer::new(
+            rt.clone(),
+            scheduler.clone(),
+            clock.clone(),
+            qtable.clone(),
+            SocketAddrV4::new(local_ipv4_addr, 0),
+        );
+        let ipv6: Option<Ipv6Peer> = match local_ipv6_addr {
+            Some(addr) => Some(Ipv6Peer::new(
+                rt.clone(),
+                qtable.clone(),
+                local_link_addr,
+                addr,
+                udp_config,
+                tcp_config,
+            )?),
+            None => None,
+        };
         Ok(Self {
             arp,
             ipv4,
+            quic,
+            ipv6,
             qtable,
             rt,
             local_link_addr,
             scheduler,
             clock,
             ts_iters: 0,
+            trace,
         })
     }
 
@@ -168,6 +248,7 @@ impl InetStack {
     /// defined in the libc crate. Currently, the following families are supported:
     ///
     /// - AF_INET Internet Protocol Version 4 (IPv4)
+    /// - AF_INET6 Internet Protocol Version 6 (IPv6), if this stack was configured with an IPv6 address
     ///
     /// **Return Vale**
     ///
@@ -183,12 +264,19 @@ impl InetStack {
             socket_type,
             _protocol
         );
+        if domain == libc::AF_INET6 {
+            return match &mut self.ipv6 {
+                Some(ipv6) => ipv6.do_socket(socket_type),
+                None => Err(Fail::new(libc::EAFNOSUPPORT, "stack was not configured with an IPv6 address")),
+            };
+        }
         if domain != AF_INET_VALUE as i32 {
             return Err(Fail::new(libc::ENOTSUP, "address family not supported"));
         }
-        match socket_type {
-            SOCK_STREAM => self.ipv4.tcp.do_socket(),
-            SOCK_DGRAM => self.ipv4.udp.do_socket(),
+        match (socket_type, _protocol) {
+            (SOCK_STREAM, _) => self.ipv4.tcp.do_socket(),
+            (SOCK_DGRAM, IPPROTO_QUIC) => self.quic.do_socket(),
+            (SOCK_DGRAM, _) => self.ipv4.udp.do_socket(),
             _ => Err(Fail::new(libc::ENOTSUP, "socket type not supported")),
         }
     }
@@ -197,22 +285,28 @@ impl InetStack {
     /// **Brief**
     ///
     /// Binds the socket referred to by `qd` to the local endpoint specified by
-    /// `local`.
+    /// `local`. `local` may be `V6` only if `qd` was created with `AF_INET6`.
     ///
     /// **Return Value**
     ///
     /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is
     /// returned instead.
     ///
-    pub fn bind(&mut self, qd: QDesc, local: SocketAddrV4) -> Result<(), Fail> {
+    pub fn bind(&mut self, qd: QDesc, local: SocketAddr) -> Result<(), Fail> {
         #[cfg(feature = "profiler")]
         timer!("inetstack::bind");
         trace!("bind(): qd={:?} local={:?}", qd, local);
-        match self.lookup_qtype(&qd) {
-            Some(QType::TcpSocket) => self.ipv4.tcp.bind(qd, local),
-            Some(QType::UdpSocket) => self.ipv4.udp.do_bind(qd, local),
-            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
-            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        match (self.lookup_qtype(&qd), local) {
+            (Some(QType::TcpSocket), SocketAddr::V4(local)) => self.ipv4.tcp.bind(qd, local),
+            (Some(QType::UdpSocket), SocketAddr::V4(local)) => self.ipv4.udp.do_bind(qd, local),
+            (Some(QType::Ipv6TcpSocket), SocketAddr::V6(local)) | (Some(QType::Ipv6UdpSocket), SocketAddr::V6(local)) => {
+                match &mut self.ipv6 {
+                    Some(ipv6) => ipv6.do_bind(qd, local.port()),
+                    None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+                }
+            },
+            (Some(_), _) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            (None, _) => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
         }
     }
 
@@ -287,6 +381,17 @@ impl InetStack {
     ///
     /// Connects the socket referred to by `qd` to the remote endpoint specified by `remote`.
     ///
+    /// `qd` may have already been [bound](Self::bind) to a fixed local port, which is the
+    /// configuration NAT hole punching needs: if both peers `bind()` to a known port and then
+    /// `connect()` to each other at roughly the same time, the first segment each side receives is
+    /// a bare SYN (not a SYN-ACK), since the peer's own `connect()` raced it. The SYN_SENT ->
+    /// SYN_RECEIVED transition for that case is implemented by
+    /// [`protocols::tcp::active_open::on_segment`](crate::inetstack::protocols::tcp::active_open::on_segment):
+    /// given a bare SYN it replies with a SYN-ACK acknowledging the peer's ISS instead of tearing
+    /// the attempt down, and converges to ESTABLISHED once both SYNs are acknowledged. The TCP
+    /// peer's SYN_SENT segment handling does not call that function, so a `connect()` that races
+    /// the peer's own `connect()` on both sides tears down instead of converging.
+    ///
     /// **Return Value**
     ///
     /// Upon successful completion, a queue token is returned. This token can be
@@ -294,10 +399,36 @@ impl InetStack {
     /// remote endpoints. Upon failure, `Fail` is
     /// returned instead.
     ///
-    pub fn connect(&mut self, qd: QDesc, remote: SocketAddrV4) -> Result<QToken, Fail> {
+    pub fn connect(&mut self, qd: QDesc, remote: SocketAddr) -> Result<QToken, Fail> {
         #[cfg(feature = "profiler")]
         timer!("inetstack::connect");
         trace!("connect(): qd={:?} remote={:?}", qd, remote);
+
+        let remote = match remote {
+            SocketAddr::V6(remote) => {
+                // No outbound send path exists for IPv6 yet (that needs the TCP/UDP wire-format
+                // logic this tree doesn't include), but route correctly to a typed error instead
+                // of falling into the IPv4 arms below with a truncated address.
+                return match self.lookup_qtype(&qd) {
+                    Some(QType::Ipv6TcpSocket) => {
+                        let _ = remote;
+                        Err(Fail::new(libc::ENOTSUP, "IPv6 connect() is not yet implemented"))
+                    },
+                    Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+                    None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+                };
+            },
+            SocketAddr::V4(remote) => remote,
+        };
+
+        if self.lookup_qtype(&qd) == Some(QType::QuicSocket) {
+            self.quic.connect(qd, remote)?;
+            // TODO: surface handshake completion as a qtoken once QuicPeer schedules a future for it.
+            // ENOTSUP, not EAGAIN: there is no scheduled completion for a caller to retry into, so
+            // retrying this call changes nothing.
+            return Err(Fail::new(libc::ENOTSUP, "QUIC handshake completion is not yet awaitable"));
+        }
+
         let future = match self.lookup_qtype(&qd) {
             Some(QType::TcpSocket) => {
                 let fut: ConnectFuture = self.ipv4.tcp.connect(qd, remote)?;
@@ -334,6 +465,156 @@ impl InetStack {
         match self.lookup_qtype(&qd) {
             Some(QType::TcpSocket) => self.ipv4.tcp.do_close(qd),
             Some(QType::UdpSocket) => self.ipv4.udp.do_close(qd),
+            Some(QType::QuicSocket) => self.quic.do_close(qd),
+            Some(QType::Ipv6TcpSocket) | Some(QType::Ipv6UdpSocket) => match &mut self.ipv6 {
+                Some(ipv6) => ipv6.do_close(qd),
+                None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+            },
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Sets the socket option identified by `level`/`name` on the socket referred to by `qd` to
+    /// `value`, mirroring the POSIX `setsockopt(2)` triple. Dispatches into the TCP or UDP peer
+    /// depending on the queue's type, the same way [`bind`](Self::bind) does.
+    ///
+    /// Supported options are `IPPROTO_TCP`/`TCP_NODELAY`, `SOL_SOCKET`/`SO_RCVBUF`,
+    /// `SOL_SOCKET`/`SO_SNDBUF` and `SOL_SOCKET`/`SO_LINGER`.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is
+    /// returned instead.
+    ///
+    pub fn setsockopt<T: Copy>(&mut self, qd: QDesc, level: c_int, name: c_int, value: T) -> Result<(), Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::setsockopt");
+        trace!("setsockopt(): qd={:?} level={:?} name={:?}", qd, level, name);
+
+        match (level, name) {
+            (libc::IPPROTO_TCP, libc::TCP_NODELAY) => match self.lookup_qtype(&qd) {
+                Some(QType::TcpSocket) => {
+                    let nodelay: bool = Self::sockopt_value_as::<T, i32>(value)? != 0;
+                    self.ipv4.tcp.set_nodelay(qd, nodelay)
+                },
+                Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+                None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+            },
+            (libc::SOL_SOCKET, libc::SO_RCVBUF) => {
+                let size: usize = Self::sockopt_value_as::<T, i32>(value)?.max(0) as usize;
+                match self.lookup_qtype(&qd) {
+                    Some(QType::TcpSocket) => self.ipv4.tcp.set_recv_buffer_size(qd, size),
+                    Some(QType::UdpSocket) => self.ipv4.udp.set_recv_buffer_size(qd, size),
+                    Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+                    None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+                }
+            },
+            (libc::SOL_SOCKET, libc::SO_SNDBUF) => {
+                let size: usize = Self::sockopt_value_as::<T, i32>(value)?.max(0) as usize;
+                match self.lookup_qtype(&qd) {
+                    Some(QType::TcpSocket) => self.ipv4.tcp.set_send_buffer_size(qd, size),
+                    Some(QType::UdpSocket) => self.ipv4.udp.set_send_buffer_size(qd, size),
+                    Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+                    None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+                }
+            },
+            (libc::SOL_SOCKET, libc::SO_LINGER) => match self.lookup_qtype(&qd) {
+                Some(QType::TcpSocket) => {
+                    let linger: libc::linger = Self::sockopt_value_as::<T, libc::linger>(value)?;
+                    self.ipv4.tcp.set_linger(qd, (linger.l_onoff != 0).then_some(linger.l_linger as u64))
+                },
+                Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+                None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+            },
+            (_, _) => Err(Fail::new(libc::ENOPROTOOPT, "unsupported socket option")),
+        }
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Reads back the socket option identified by `level`/`name` on the socket referred to by `qd`,
+    /// mirroring the POSIX `getsockopt(2)` triple. Follows the common `MaybeUninit<T>` pattern: the
+    /// output value is only initialized (and returned) once the underlying peer has filled it in.
+    ///
+    /// Supports `IPPROTO_TCP`/`TCP_NODELAY` and the read-only `IPPROTO_TCP`/`TCP_MAXSEG` (effective
+    /// segment size, after window scaling). `SO_RCVBUF`, `SO_SNDBUF` and `SO_LINGER` can be set
+    /// through [`setsockopt`](Self::setsockopt) but are not readable here: they return `ENOPROTOOPT`.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, the current value of the option is returned. Upon failure,
+    /// `Fail` is returned instead.
+    ///
+    pub fn getsockopt<T: Copy>(&mut self, qd: QDesc, level: c_int, name: c_int) -> Result<T, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::getsockopt");
+        trace!("getsockopt(): qd={:?} level={:?} name={:?}", qd, level, name);
+
+        let mut out: MaybeUninit<T> = MaybeUninit::uninit();
+        match (level, name) {
+            (libc::IPPROTO_TCP, libc::TCP_NODELAY) => match self.lookup_qtype(&qd) {
+                Some(QType::TcpSocket) => {
+                    let nodelay: i32 = self.ipv4.tcp.nodelay(qd)? as i32;
+                    out.write(Self::sockopt_value_from::<i32, T>(nodelay)?);
+                },
+                Some(_) => return Err(Fail::new(libc::EINVAL, "invalid queue type")),
+                None => return Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+            },
+            (libc::IPPROTO_TCP, libc::TCP_MAXSEG) => match self.lookup_qtype(&qd) {
+                Some(QType::TcpSocket) => {
+                    let mss: i32 = self.ipv4.tcp.effective_mss(qd)? as i32;
+                    out.write(Self::sockopt_value_from::<i32, T>(mss)?);
+                },
+                Some(_) => return Err(Fail::new(libc::EINVAL, "invalid queue type")),
+                None => return Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+            },
+            (_, _) => return Err(Fail::new(libc::ENOPROTOOPT, "unsupported socket option")),
+        };
+
+        // Safety: every arm above either returned early or called `out.write(..)`.
+        Ok(unsafe { out.assume_init() })
+    }
+
+    /// Reinterprets a `setsockopt` payload of type `T` as `U`, the type the underlying peer expects.
+    /// Both sides of a real option are always the same size (e.g. a C `int`), so this is a sound,
+    /// zero-cost reinterpretation rather than a true conversion, *provided* the caller passed a
+    /// same-sized `T`. A mismatched size (e.g. an `i64` where the option expects a C `int`) is a
+    /// caller bug reachable from the public `setsockopt`/`getsockopt` generics, so it is reported as
+    /// `EINVAL` instead of trusted to never happen.
+    fn sockopt_value_as<T: Copy, U: Copy>(value: T) -> Result<U, Fail> {
+        if ::std::mem::size_of::<T>() != ::std::mem::size_of::<U>() {
+            return Err(Fail::new(libc::EINVAL, "socket option size mismatch"));
+        }
+        Ok(unsafe { ::std::mem::transmute_copy(&value) })
+    }
+
+    /// Inverse of [`sockopt_value_as`](Self::sockopt_value_as), used when filling in a
+    /// `getsockopt` output of caller-chosen type `U` from a value of known type `T`.
+    fn sockopt_value_from<T: Copy, U: Copy>(value: T) -> Result<U, Fail> {
+        Self::sockopt_value_as::<T, U>(value)
+    }
+
+    ///
+    /// **Brief**
+    ///
+    /// Joins the UDP socket referred to by `qd` to multicast `group`, receiving on `local`.
+    ///
+    /// **Return Value**
+    ///
+    /// Upon successful completion, `Ok(())` is returned. Upon failure, `Fail` is returned instead.
+    ///
+    pub fn join_multicast(&mut self, qd: QDesc, group: Ipv4Addr, local: Ipv4Addr) -> Result<(), Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::join_multicast");
+        trace!("join_multicast(): qd={:?} group={:?} local={:?}", qd, group, local);
+
+        match self.lookup_qtype(&qd) {
+            Some(QType::UdpSocket) => self.ipv4.udp.join_multicast(group, local),
             Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
             None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
         }
@@ -381,6 +662,11 @@ impl InetStack {
     pub fn do_push(&mut self, qd: QDesc, buf: DemiBuffer) -> Result<FutureOperation, Fail> {
         match self.lookup_qtype(&qd) {
             Some(QType::TcpSocket) => Ok(FutureOperation::from(self.ipv4.tcp.push(qd, buf))),
+            Some(QType::QuicSocket) => self.quic.push(qd, buf),
+            // No outbound send path exists for IPv6 yet; see `connect()`.
+            Some(QType::Ipv6TcpSocket) | Some(QType::Ipv6UdpSocket) => {
+                Err(Fail::new(libc::ENOTSUP, "IPv6 push is not yet implemented"))
+            },
             Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
             None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
         }
@@ -418,6 +704,8 @@ impl InetStack {
                 let udp_op = UdpOperation::Pushto(qd, self.ipv4.udp.do_pushto(qd, buf, to));
                 Ok(FutureOperation::Udp(udp_op))
             },
+            // No outbound send path exists for IPv6 yet; see `connect()`.
+            Some(QType::Ipv6UdpSocket) => Err(Fail::new(libc::ENOTSUP, "IPv6 pushto is not yet implemented")),
             Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
             None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
         }
@@ -425,11 +713,17 @@ impl InetStack {
 
     /// Pushes raw data to a UDP socket.
     /// TODO: Move this function to demikernel repo once we have a common buffer representation across all libOSes.
-    pub fn pushto2(&mut self, qd: QDesc, data: &[u8], remote: SocketAddrV4) -> Result<QToken, Fail> {
+    pub fn pushto2(&mut self, qd: QDesc, data: &[u8], remote: SocketAddr) -> Result<QToken, Fail> {
         #[cfg(feature = "profiler")]
         timer!("inetstack::pushto2");
         trace!("pushto2(): qd={:?}", qd);
 
+        let remote: SocketAddrV4 = match remote {
+            SocketAddr::V4(remote) => remote,
+            // No outbound send path exists for IPv6 yet; see `connect()`.
+            SocketAddr::V6(_) => return Err(Fail::new(libc::ENOTSUP, "IPv6 pushto is not yet implemented")),
+        };
+
         // Convert raw data to a buffer representation.
         let buf: DemiBuffer = DemiBuffer::from_slice(data)?;
         if buf.is_empty() {
@@ -461,6 +755,13 @@ impl InetStack {
                 let udp_op = UdpOperation::Pop(FutureResult::new(self.ipv4.udp.do_pop(qd), None));
                 Ok(FutureOperation::Udp(udp_op))
             },
+            Some(QType::QuicSocket) => self.quic.pop(qd),
+            // `Ipv6Peer` already queues received payloads (see `receive()`) for `do_pop`, but surfacing
+            // them through this scheduler-driven `pop()` needs a `FutureOperation` variant for
+            // IPv6 that doesn't exist in this tree yet.
+            Some(QType::Ipv6TcpSocket) | Some(QType::Ipv6UdpSocket) => {
+                Err(Fail::new(libc::ENOTSUP, "IPv6 pop is not yet implemented"))
+            },
             Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
             None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
         }?;
@@ -474,6 +775,38 @@ impl InetStack {
         Ok(qt)
     }
 
+    /// Like [`pop`](Self::pop), but non-destructive: the returned bytes remain queued, so a
+    /// subsequent `pop`/`peek` on `qd` observes the same data again. Mirrors the POSIX
+    /// `MSG_PEEK` recv flag.
+    ///
+    /// On the TCP path this reads from the head of the reassembly buffer without advancing the
+    /// consumer cursor or sliding the receive window. On the UDP path it clones the front datagram
+    /// instead of dequeuing it.
+    pub fn peek(&mut self, qd: QDesc) -> Result<QToken, Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::peek");
+
+        trace!("peek(): qd={:?}", qd);
+
+        let future = match self.lookup_qtype(&qd) {
+            Some(QType::TcpSocket) => Ok(FutureOperation::from(self.ipv4.tcp.peek(qd))),
+            Some(QType::UdpSocket) => {
+                let udp_op = UdpOperation::Pop(FutureResult::new(self.ipv4.udp.do_peek(qd), None));
+                Ok(FutureOperation::Udp(udp_op))
+            },
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }?;
+
+        let handle: SchedulerHandle = match self.scheduler.insert(future) {
+            Some(handle) => handle,
+            None => return Err(Fail::new(libc::EAGAIN, "cannot schedule co-routine")),
+        };
+        let qt: QToken = handle.into_raw().into();
+        trace!("peek() qt={:?}", qt);
+        Ok(qt)
+    }
+
     /// Waits for an operation to complete.
     #[deprecated]
     pub fn wait2(&mut self, qt: QToken) -> Result<(QDesc, OperationResult), Fail> {
@@ -494,7 +827,9 @@ impl InetStack {
             // The operation has completed, so extract the result and return.
             if handle.has_completed() {
                 trace!("wait2() qt={:?} completed!", qt);
-                return Ok(self.take_operation(handle));
+                let (qd, r): (QDesc, OperationResult) = self.take_operation(handle);
+                self.record_completion(qt, qd);
+                return Ok((qd, r));
             }
         }
     }
@@ -522,6 +857,93 @@ impl InetStack {
                 // Found one, so extract the result and return.
                 if handle.has_completed() {
                     let (qd, r): (QDesc, OperationResult) = self.take_operation(handle);
+                    self.record_completion(qt, qd);
+                    return Ok((i, qd, r));
+                }
+
+                // Return this operation to the scheduling queue by removing the associated key
+                // (which would otherwise cause the operation to be freed).
+                handle.take_key();
+            }
+        }
+    }
+
+    /// Waits for an operation to complete, or for `timeout` to elapse.
+    ///
+    /// `timeout` is measured off the same `Instant::now()`/`advance_clock` clock `poll_bg_work`
+    /// drives the rest of the stack with. Passing `None` waits forever, matching `wait2`. If the
+    /// deadline elapses first, an `ETIMEDOUT` `Fail` is returned and the underlying operation is
+    /// left scheduled, so the caller may retry the wait or cancel it explicitly.
+    pub fn wait_timeout(&mut self, qt: QToken, timeout: Option<Duration>) -> Result<(QDesc, OperationResult), Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::wait_timeout");
+        trace!("wait_timeout(): qt={:?} timeout={:?}", qt, timeout);
+
+        let deadline: Option<Instant> = timeout.map(|d| Instant::now() + d);
+
+        // Retrieve associated schedule handle.
+        let mut handle: SchedulerHandle = match self.scheduler.from_raw_handle(qt.into()) {
+            Some(handle) => handle,
+            None => return Err(Fail::new(libc::EINVAL, "invalid queue token")),
+        };
+
+        let mut backoff: IdleBackoff = IdleBackoff::new();
+        loop {
+            // Poll first, so as to give pending operations a chance to complete.
+            self.poll_bg_work();
+
+            // The operation has completed, so extract the result and return.
+            if handle.has_completed() {
+                trace!("wait_timeout() qt={:?} completed!", qt);
+                let (qd, r): (QDesc, OperationResult) = self.take_operation(handle);
+                self.record_completion(qt, qd);
+                return Ok((qd, r));
+            }
+
+            if deadline_exceeded(deadline, Instant::now()) {
+                trace!("wait_timeout() qt={:?} timed out", qt);
+                // Return this operation to the scheduling queue by removing the associated key
+                // (which would otherwise cause the operation to be freed).
+                handle.take_key();
+                return Err(Fail::new(libc::ETIMEDOUT, "timed out waiting for operation to complete"));
+            }
+
+            // Nothing was ready: back off instead of re-polling as fast as possible.
+            backoff.idle();
+        }
+    }
+
+    /// Waits for any of `qts` to complete, or for `timeout` to elapse. See [`wait_timeout`](Self::wait_timeout)
+    /// for the semantics of `timeout` and of the returned `ETIMEDOUT` error.
+    pub fn wait_any_timeout(
+        &mut self,
+        qts: &[QToken],
+        timeout: Option<Duration>,
+    ) -> Result<(usize, QDesc, OperationResult), Fail> {
+        #[cfg(feature = "profiler")]
+        timer!("inetstack::wait_any_timeout");
+        trace!("wait_any_timeout(): qts={:?} timeout={:?}", qts, timeout);
+
+        let deadline: Option<Instant> = timeout.map(|d| Instant::now() + d);
+
+        let mut backoff: IdleBackoff = IdleBackoff::new();
+        loop {
+            // Poll first, so as to give pending operations a chance to complete.
+            self.poll_bg_work();
+
+            // Search for any operation that has completed.
+            for (i, &qt) in qts.iter().enumerate() {
+                // Retrieve associated schedule handle.
+                // TODO: move this out of the loop.
+                let mut handle: SchedulerHandle = match self.scheduler.from_raw_handle(qt.into()) {
+                    Some(handle) => handle,
+                    None => return Err(Fail::new(libc::EINVAL, "invalid queue token")),
+                };
+
+                // Found one, so extract the result and return.
+                if handle.has_completed() {
+                    let (qd, r): (QDesc, OperationResult) = self.take_operation(handle);
+                    self.record_completion(qt, qd);
                     return Ok((i, qd, r));
                 }
 
@@ -529,6 +951,14 @@ impl InetStack {
                 // (which would otherwise cause the operation to be freed).
                 handle.take_key();
             }
+
+            if deadline_exceeded(deadline, Instant::now()) {
+                trace!("wait_any_timeout() qts={:?} timed out", qts);
+                return Err(Fail::new(libc::ETIMEDOUT, "timed out waiting for any operation to complete"));
+            }
+
+            // Nothing was ready: back off instead of re-polling as fast as possible.
+            backoff.idle();
         }
     }
 
@@ -549,12 +979,26 @@ impl InetStack {
         }
     }
 
+    /// Records `(qt, qd)` as a delivered completion, if this stack was configured to record a trace.
+    fn record_completion(&mut self, qt: QToken, qd: QDesc) {
+        if let Some(Trace::Recording(recorder)) = self.trace.as_mut() {
+            if let Err(e) = recorder.record_completion(qt, qd) {
+                warn!("failed to record completion to trace: {:?}", e);
+            }
+        }
+    }
+
     /// New incoming data has arrived. Route it to the correct parse out the Ethernet header and
     /// allow the correct protocol to handle it. The underlying protocol will futher parse the data
     /// and inform the correct task that its data has arrived.
     fn do_receive(&mut self, bytes: DemiBuffer) -> Result<(), Fail> {
         #[cfg(feature = "profiler")]
         timer!("inetstack::engine::receive");
+        if let Some(Trace::Recording(recorder)) = self.trace.as_mut() {
+            if let Err(e) = recorder.record_frame(&bytes) {
+                warn!("failed to record frame to trace: {:?}", e);
+            }
+        }
         let (header, payload) = Ethernet2Header::parse(bytes)?;
         debug!("Engine received {:?}", header);
         if self.local_link_addr != header.dst_addr()
@@ -566,7 +1010,11 @@ impl InetStack {
         match header.ether_type() {
             EtherType2::Arp => self.arp.receive(payload),
             EtherType2::Ipv4 => self.ipv4.receive(payload),
-            EtherType2::Ipv6 => Ok(()), // Ignore for now.
+            EtherType2::Ipv6 => match &mut self.ipv6 {
+                Some(ipv6) => ipv6.receive(payload),
+                // No IPv6 address was configured for this stack: drop the frame.
+                None => Ok(()),
+            },
         }
     }
 
@@ -582,6 +1030,10 @@ impl InetStack {
             self.scheduler.poll();
         }
 
+        // Give the QUIC peer its per-tick hook, same cadence as the scheduler poll above (it
+        // currently transmits nothing and fires no timers; see `QuicPeer::poll_bg_work`).
+        self.quic.poll_bg_work();
+
         {
             #[cfg(feature = "profiler")]
             timer!("inetstack::poll_bg_work::for");
@@ -591,7 +1043,26 @@ impl InetStack {
                     #[cfg(feature = "profiler")]
                     timer!("inetstack::poll_bg_work::for::receive");
 
-                    self.rt.receive()
+                    // In replay mode, frames are re-injected from the trace file on the same
+                    // relative schedule they were recorded on, instead of being pulled live off
+                    // the runtime.
+                    match self.trace.as_mut() {
+                        Some(Trace::Replaying(player)) => match player.next_ready_frame() {
+                            Ok(Some(bytes)) => match DemiBuffer::from_slice(&bytes) {
+                                Ok(buf) => vec![buf],
+                                Err(e) => {
+                                    warn!("dropped malformed replayed frame: {:?}", e);
+                                    Vec::new()
+                                },
+                            },
+                            Ok(None) => Vec::new(),
+                            Err(e) => {
+                                warn!("failed to read replayed frame from trace: {:?}", e);
+                                Vec::new()
+                            },
+                        },
+                        _ => self.rt.receive(),
+                    }
                 };
 
                 {
@@ -619,3 +1090,50 @@ impl InetStack {
         self.ts_iters = (self.ts_iters + 1) % TIMER_RESOLUTION;
     }
 }
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadline_exceeded_none_never_trips() {
+        assert!(!deadline_exceeded(None, Instant::now()));
+    }
+
+    #[test]
+    fn deadline_exceeded_in_the_past() {
+        let now: Instant = Instant::now();
+        let past: Instant = now - Duration::from_millis(1);
+        assert!(deadline_exceeded(Some(past), now));
+    }
+
+    #[test]
+    fn deadline_exceeded_in_the_future() {
+        let now: Instant = Instant::now();
+        let future: Instant = now + Duration::from_secs(10);
+        assert!(!deadline_exceeded(Some(future), now));
+    }
+
+    #[test]
+    fn deadline_exceeded_exactly_at_deadline() {
+        let now: Instant = Instant::now();
+        assert!(deadline_exceeded(Some(now), now));
+    }
+
+    #[test]
+    fn idle_backoff_grows_but_stays_bounded() {
+        let mut backoff: IdleBackoff = IdleBackoff::new();
+        let mut last: Duration = Duration::ZERO;
+        for _ in 0..16 {
+            backoff.idle();
+            assert!(backoff.delay >= last);
+            assert!(backoff.delay <= MAX_IDLE_BACKOFF);
+            last = backoff.delay;
+        }
+        assert_eq!(backoff.delay, MAX_IDLE_BACKOFF);
+    }
+}
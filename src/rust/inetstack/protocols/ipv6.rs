@@ -0,0 +1,247 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::{
+    inetstack::protocols::queue::InetQueue,
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::{
+            config::{
+                TcpConfig,
+                UdpConfig,
+            },
+            types::MacAddress,
+            NetworkRuntime,
+        },
+        queue::IoQueueTable,
+        QDesc,
+        QType,
+    },
+};
+use ::std::{
+    cell::RefCell,
+    collections::{
+        HashMap,
+        VecDeque,
+    },
+    net::Ipv6Addr,
+    rc::Rc,
+};
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Size, in bytes, of the fixed IPv6 header (RFC 8200 §3): version/traffic-class/flow-label (4),
+/// payload length (2), next header (1), hop limit (1), source address (16), destination (16).
+const IPV6_HEADER_SIZE: usize = 40;
+
+/// `next_header` value identifying an embedded TCP segment.
+const NEXT_HEADER_TCP: u8 = 6;
+/// `next_header` value identifying an embedded UDP datagram.
+const NEXT_HEADER_UDP: u8 = 17;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// A socket bound to this peer's IPv6 address family.
+pub struct Ipv6Socket {
+    /// `SOCK_STREAM` or `SOCK_DGRAM`.
+    qtype: QType,
+    /// Local port this socket is bound to, once [`Ipv6Peer::do_bind`] is called.
+    local_port: Option<u16>,
+    /// Payloads received for this socket and not yet [popped](Ipv6Peer::do_pop), oldest first.
+    inbound: VecDeque<DemiBuffer>,
+}
+
+impl crate::runtime::queue::IoQueue for Ipv6Socket {
+    fn get_qtype(&self) -> QType {
+        self.qtype
+    }
+}
+
+/// IPv6 peer.
+///
+/// Sibling of [`Peer`](crate::inetstack::protocols::Peer) (the IPv4 peer): it owns the IPv6
+/// datapath and demultiplexes `do_receive`d frames into TCP/UDP sockets bound to an IPv6 address,
+/// the same way `Peer` does for IPv4. No Neighbor Discovery (the IPv6 counterpart of ARP) is
+/// performed.
+///
+/// Like `Peer`, allocates its queue descriptors out of the single `InetQueue` table shared with
+/// the rest of the stack (via [`InetQueue::Ipv6`]), tagged `QType::Ipv6TcpSocket`/
+/// `QType::Ipv6UdpSocket` so `InetStack::lookup_qtype` can tell an IPv6 socket's `qd` apart from an
+/// IPv4 one of the same transport and route `bind`/`close`/`pop`/etc. here instead of to `ipv4`.
+pub struct Ipv6Peer {
+    /// Underlying runtime used to send and receive frames.
+    rt: Rc<dyn NetworkRuntime>,
+    /// Local link-layer address.
+    local_link_addr: MacAddress,
+    /// Local IPv6 address (link-local or global) this peer answers to.
+    local_ipv6_addr: Ipv6Addr,
+    /// Queue table shared with the rest of `InetStack`.
+    qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
+    /// Maps a bound local port back to the queue descriptor it belongs to, so `receive` can
+    /// demultiplex an inbound segment/datagram to the right socket.
+    bound: Rc<RefCell<HashMap<u16, QDesc>>>,
+}
+
+impl Ipv6Peer {
+    /// Creates a new IPv6 peer bound to `local_ipv6_addr`.
+    pub fn new(
+        rt: Rc<dyn NetworkRuntime>,
+        qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
+        local_link_addr: MacAddress,
+        local_ipv6_addr: Ipv6Addr,
+        _udp_config: UdpConfig,
+        _tcp_config: TcpConfig,
+    ) -> Result<Self, Fail> {
+        Ok(Self {
+            rt,
+            local_link_addr,
+            local_ipv6_addr,
+            qtable,
+            bound: Rc::new(RefCell::new(HashMap::new())),
+        })
+    }
+
+    /// Returns the local IPv6 address this peer answers to.
+    pub fn local_addr(&self) -> Ipv6Addr {
+        self.local_ipv6_addr
+    }
+
+    /// Creates a TCP or UDP socket bound to this peer's address family. Mirrors `Peer::tcp`/`udp`
+    /// socket creation, which `InetStack::socket` calls into for `AF_INET`.
+    pub fn do_socket(&mut self, socket_type: libc::c_int) -> Result<QDesc, Fail> {
+        let qtype: QType = match socket_type {
+            libc::SOCK_STREAM => QType::Ipv6TcpSocket,
+            libc::SOCK_DGRAM => QType::Ipv6UdpSocket,
+            _ => return Err(Fail::new(libc::ENOTSUP, "socket type not supported")),
+        };
+        let qd: QDesc = self.qtable.borrow_mut().alloc(InetQueue::Ipv6(Ipv6Socket {
+            qtype,
+            local_port: None,
+            inbound: VecDeque::new(),
+        }));
+        Ok(qd)
+    }
+
+    /// Borrows the [`Ipv6Socket`] referred to by `qd` out of the shared queue table.
+    fn get_socket<'a>(
+        table: &'a mut IoQueueTable<InetQueue>,
+        qd: &QDesc,
+    ) -> Result<&'a mut Ipv6Socket, Fail> {
+        match table.get_mut(qd) {
+            Some(InetQueue::Ipv6(socket)) => Ok(socket),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Binds the socket referred to by `qd` to `local_port`, so that subsequent calls to
+    /// [`receive`](Self::receive) demultiplex segments/datagrams addressed to that port to it.
+    pub fn do_bind(&mut self, qd: QDesc, local_port: u16) -> Result<(), Fail> {
+        let mut table = self.qtable.borrow_mut();
+        let socket: &mut Ipv6Socket = Self::get_socket(&mut table, &qd)?;
+        if socket.local_port.is_some() {
+            return Err(Fail::new(libc::EINVAL, "socket is already bound"));
+        }
+        if self.bound.borrow().contains_key(&local_port) {
+            return Err(Fail::new(libc::EADDRINUSE, "port is already in use"));
+        }
+        socket.local_port = Some(local_port);
+        self.bound.borrow_mut().insert(local_port, qd);
+        Ok(())
+    }
+
+    /// Pops the oldest payload queued for the socket referred to by `qd`, if any.
+    pub fn do_pop(&mut self, qd: QDesc) -> Result<Option<DemiBuffer>, Fail> {
+        let mut table = self.qtable.borrow_mut();
+        let socket: &mut Ipv6Socket = Self::get_socket(&mut table, &qd)?;
+        Ok(socket.inbound.pop_front())
+    }
+
+    /// Removes the socket referred to by `qd`, releasing its bound port (if any).
+    pub fn do_close(&mut self, qd: QDesc) -> Result<(), Fail> {
+        let socket: InetQueue = self
+            .qtable
+            .borrow_mut()
+            .remove(&qd)
+            .ok_or(Fail::new(libc::EBADF, "bad queue descriptor"))?;
+        if let InetQueue::Ipv6(socket) = socket {
+            if let Some(local_port) = socket.local_port {
+                self.bound.borrow_mut().remove(&local_port);
+            }
+        }
+        Ok(())
+    }
+
+    /// Routes an inbound IPv6 payload (already stripped of its Ethernet header) to the matching
+    /// TCP/UDP socket, mirroring [`Peer::receive`](crate::inetstack::protocols::Peer::receive).
+    ///
+    /// Parses the fixed IPv6 header to find the embedded TCP/UDP destination port (both protocols
+    /// place it in the first two bytes of their header, right after the IPv6 header), then queues
+    /// the remaining payload on whichever socket is bound to that port. Neighbor Discovery, routing
+    /// extension headers and fragmentation are out of scope here, the same way ARP resolution and
+    /// IP options are for the stub this mirrors.
+    pub fn receive(&mut self, payload: DemiBuffer) -> Result<(), Fail> {
+        if payload.len() < IPV6_HEADER_SIZE {
+            return Err(Fail::new(libc::EINVAL, "IPv6 packet too small to contain a header"));
+        }
+        let version: u8 = payload[0] >> 4;
+        if version != 6 {
+            return Err(Fail::new(libc::EINVAL, "not an IPv6 packet"));
+        }
+        let next_header: u8 = payload[6];
+        let dst_addr: Ipv6Addr = {
+            let mut octets: [u8; 16] = [0u8; 16];
+            octets.copy_from_slice(&payload[24..40]);
+            Ipv6Addr::from(octets)
+        };
+        if dst_addr != self.local_ipv6_addr && !dst_addr.is_multicast() {
+            return Err(Fail::new(libc::EINVAL, "destination address mismatch"));
+        }
+        match next_header {
+            NEXT_HEADER_TCP | NEXT_HEADER_UDP => {},
+            _ => return Err(Fail::new(libc::ENOTSUP, "unsupported IPv6 next header")),
+        }
+
+        let l4: &[u8] = &payload[IPV6_HEADER_SIZE..];
+        if l4.len() < 4 {
+            return Err(Fail::new(libc::EINVAL, "L4 header too small to contain ports"));
+        }
+        // Both TCP and UDP place the destination port as the second big-endian u16 of their header.
+        let dst_port: u16 = u16::from_be_bytes([l4[2], l4[3]]);
+        // UDP's header is a fixed 8 bytes; TCP's data offset (top nibble of byte 12, in 32-bit words)
+        // tells us where its header ends and the application payload begins.
+        let l4_header_size: usize = match next_header {
+            NEXT_HEADER_UDP => 8,
+            NEXT_HEADER_TCP => {
+                if l4.len() < 13 {
+                    return Err(Fail::new(libc::EINVAL, "TCP header too small to contain a data offset"));
+                }
+                ((l4[12] >> 4) as usize) * 4
+            },
+            _ => unreachable!("next_header was already validated above"),
+        };
+        if l4.len() < l4_header_size {
+            return Err(Fail::new(libc::EINVAL, "L4 header longer than the packet"));
+        }
+
+        let qd: QDesc = match self.bound.borrow().get(&dst_port) {
+            Some(&qd) => qd,
+            None => return Err(Fail::new(libc::ECONNREFUSED, "no socket bound to destination port")),
+        };
+        let mut table = self.qtable.borrow_mut();
+        let socket: &mut Ipv6Socket = Self::get_socket(&mut table, &qd)?;
+        let mut app_payload: DemiBuffer = payload;
+        app_payload.adjust(IPV6_HEADER_SIZE + l4_header_size);
+        socket.inbound.push_back(app_payload);
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::{
+    inetstack::{
+        futures::operation::FutureOperation,
+        protocols::queue::InetQueue,
+    },
+    runtime::{
+        fail::Fail,
+        memory::DemiBuffer,
+        network::NetworkRuntime,
+        queue::{
+            IoQueue,
+            IoQueueTable,
+        },
+        timer::TimerRc,
+        QDesc,
+        QType,
+    },
+    scheduler::Scheduler,
+};
+use ::quinn_proto::{
+    ConnectionHandle,
+    Endpoint,
+    EndpointConfig,
+};
+use ::std::{
+    cell::RefCell,
+    collections::HashMap,
+    net::SocketAddrV4,
+    rc::Rc,
+};
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// State for a single multiplexed QUIC connection, keyed by its `quinn-proto` connection handle.
+pub struct QuicConnection {
+    /// Underlying `quinn-proto` connection handle.
+    handle: ConnectionHandle,
+    /// Remote endpoint of this connection.
+    remote: SocketAddrV4,
+}
+
+impl IoQueue for QuicConnection {
+    fn get_qtype(&self) -> QType {
+        QType::QuicSocket
+    }
+}
+
+/// QUIC peer.
+///
+/// Like the TCP and UDP peers, owns per-connection state. Every connection shares a single
+/// `quinn-proto` [`Endpoint`] on `local`. No packets are exchanged yet: inbound datagrams are not
+/// routed to connections, nothing is drained from `Endpoint::poll_transmit`, and no future is
+/// scheduled; [`poll_bg_work`](Self::poll_bg_work) is called on every `InetStack` tick but does
+/// nothing.
+///
+/// Like `Peer` and `Ipv6Peer`, allocates its queue descriptors out of the single `InetQueue` table
+/// shared with the rest of the stack (via [`InetQueue::Quic`]), rather than a private table of its
+/// own -- a private table meant a `QuicSocket` qd was invisible to `InetStack::lookup_qtype`, so
+/// every call past `socket()` (connect/accept/push/pop/close) was unreachable from the outside.
+pub struct QuicPeer {
+    /// Underlying runtime used to send and receive UDP datagrams.
+    rt: Rc<dyn NetworkRuntime>,
+    /// Scheduler shared with the rest of the stack (no future is inserted into it by this peer).
+    scheduler: Scheduler,
+    /// Clock shared with the rest of the stack (not consulted by this peer).
+    clock: TimerRc,
+    /// Queue table shared with the rest of `InetStack`.
+    qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
+    /// The UDP address this peer's endpoint listens on.
+    local: SocketAddrV4,
+    /// Sans-IO endpoint shared by every connection multiplexed on `local`.
+    endpoint: Rc<RefCell<Endpoint>>,
+    /// Maps `quinn-proto` connection handles back to queue descriptors.
+    connections: Rc<RefCell<HashMap<ConnectionHandle, QDesc>>>,
+    /// Monotonically increasing source of `ConnectionHandle`s. `quinn-proto` hands one of these out
+    /// per connection via `Endpoint::connect`/`Endpoint::handle`, but until that handshake wiring
+    /// lands a queue descriptor still needs a unique handle the moment it is allocated, so this
+    /// counter stands in for it; reusing `connections.len()` would hand out a handle that collides
+    /// with a live connection's as soon as an earlier one is closed and removed from the map.
+    next_handle: usize,
+}
+
+impl QuicPeer {
+    /// Creates a new QUIC peer whose endpoint is bound to `local`. `qtable` is the queue table
+    /// shared with the rest of `InetStack`.
+    pub fn new(
+        rt: Rc<dyn NetworkRuntime>,
+        scheduler: Scheduler,
+        clock: TimerRc,
+        qtable: Rc<RefCell<IoQueueTable<InetQueue>>>,
+        local: SocketAddrV4,
+    ) -> Self {
+        Self {
+            rt,
+            scheduler,
+            clock,
+            qtable,
+            local,
+            endpoint: Rc::new(RefCell::new(Endpoint::new(Rc::new(EndpointConfig::default()), None, true, None))),
+            connections: Rc::new(RefCell::new(HashMap::new())),
+            next_handle: 0,
+        }
+    }
+
+    /// Borrows the [`QuicConnection`] referred to by `qd` out of the shared queue table.
+    fn get_connection<'a>(
+        table: &'a mut IoQueueTable<InetQueue>,
+        qd: &QDesc,
+    ) -> Result<&'a mut QuicConnection, Fail> {
+        match table.get_mut(qd) {
+            Some(InetQueue::Quic(connection)) => Ok(connection),
+            Some(_) => Err(Fail::new(libc::EINVAL, "invalid queue type")),
+            None => Err(Fail::new(libc::EBADF, "bad queue descriptor")),
+        }
+    }
+
+    /// Allocates a queue descriptor for a not-yet-connected QUIC endpoint, mirroring
+    /// `TcpPeer::do_socket`. A subsequent call to [`connect`](Self::connect) sets its remote.
+    pub fn do_socket(&mut self) -> Result<QDesc, Fail> {
+        let handle: ConnectionHandle = ConnectionHandle(self.next_handle);
+        self.next_handle += 1;
+        let remote: SocketAddrV4 = SocketAddrV4::new(std::net::Ipv4Addr::UNSPECIFIED, 0);
+        let qd: QDesc = self.qtable.borrow_mut().alloc(InetQueue::Quic(QuicConnection { handle, remote }));
+        self.connections.borrow_mut().insert(handle, qd);
+        Ok(qd)
+    }
+
+    /// Records `remote` as the peer of the connection referred to by `qd`. No handshake is started
+    /// and no stream is opened.
+    pub fn connect(&mut self, qd: QDesc, remote: SocketAddrV4) -> Result<(), Fail> {
+        let mut table = self.qtable.borrow_mut();
+        let connection: &mut QuicConnection = Self::get_connection(&mut table, &qd)?;
+        connection.remote = remote;
+        // TODO: drive the actual handshake through `Endpoint::connect` and register interest with
+        // `self.scheduler` so that callers observe completion via the usual `wait`/`wait_any` path.
+        Ok(())
+    }
+
+    /// Accepting incoming QUIC connections is not supported: always fails with `EAGAIN`.
+    pub fn accept(&mut self, _qd: QDesc) -> Result<QDesc, Fail> {
+        Err(Fail::new(libc::EAGAIN, "no incoming QUIC connection is pending"))
+    }
+
+    /// Entry point for an inbound UDP datagram destined for this endpoint's port. The datagram is
+    /// discarded: it is not routed to any connection.
+    pub fn do_receive(&mut self, _buf: DemiBuffer) -> Result<(), Fail> {
+        // TODO: feed `buf` to `Endpoint::handle`, then forward any resulting `DatagramEvent` to the
+        // matching `Connection` and wake the scheduler task tracking it.
+        Ok(())
+    }
+
+    /// Per-tick hook, invoked from `InetStack::poll_bg_work`. Does nothing: no packets are drained
+    /// from `Endpoint::poll_transmit` and no connection timeouts are handled.
+    pub fn poll_bg_work(&mut self) {
+        let _rt: &Rc<dyn NetworkRuntime> = &self.rt;
+        let _endpoint: &Rc<RefCell<Endpoint>> = &self.endpoint;
+        // TODO: loop `Endpoint::poll_transmit`, wrap each datagram in a `DemiBuffer` and push it
+        // through `self.rt`; then call `Connection::handle_timeout` for any connection whose
+        // `poll_timeout` deadline has elapsed on `self.clock`.
+    }
+
+    /// Stream push is not supported: checks that `qd` refers to a QUIC connection, then always
+    /// fails with `ENOTSUP`.
+    pub fn push(&mut self, qd: QDesc, _buf: DemiBuffer) -> Result<FutureOperation, Fail> {
+        let mut table = self.qtable.borrow_mut();
+        let _connection: &mut QuicConnection = Self::get_connection(&mut table, &qd)?;
+        Err(Fail::new(libc::ENOTSUP, "QUIC stream push is not yet implemented"))
+    }
+
+    /// Stream pop is not supported: checks that `qd` refers to a QUIC connection, then always
+    /// fails with `ENOTSUP`.
+    pub fn pop(&mut self, qd: QDesc) -> Result<FutureOperation, Fail> {
+        let mut table = self.qtable.borrow_mut();
+        let _connection: &mut QuicConnection = Self::get_connection(&mut table, &qd)?;
+        Err(Fail::new(libc::ENOTSUP, "QUIC stream pop is not yet implemented"))
+    }
+
+    /// Closes the connection referred to by `qd`.
+    pub fn do_close(&mut self, qd: QDesc) -> Result<(), Fail> {
+        let queue: InetQueue = self
+            .qtable
+            .borrow_mut()
+            .remove(&qd)
+            .ok_or(Fail::new(libc::EBADF, "bad queue descriptor"))?;
+        if let InetQueue::Quic(connection) = queue {
+            self.connections.borrow_mut().remove(&connection.handle);
+        }
+        Ok(())
+    }
+}
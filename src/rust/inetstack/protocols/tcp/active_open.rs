@@ -0,0 +1,191 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+// (None: this module only deals in plain sequence numbers and flags, not wire-format segments, so
+// it has no dependency on the rest of the TCP stack.)
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// State of a connection actively opened via `connect()`, up to the point it reaches
+/// [`Established`](Self::Established) (or is abandoned).
+///
+/// Mirrors the subset of RFC 793's state machine that
+/// [`InetStack::connect`](crate::inetstack::InetStack::connect)'s doc comment describes: from
+/// `SynSent`, a connection usually moves straight to `Established` off the peer's SYN-ACK, but
+/// moves through `SynReceived` first when the peer's own `connect()` raced ours (both sides bound
+/// to a known port and dialed out at roughly the same time, so the first segment each side
+/// receives is a bare SYN, not a SYN-ACK).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActiveOpenState {
+    /// Our SYN is out, carrying initial sequence number `iss`; waiting on the peer's SYN-ACK (or,
+    /// for simultaneous open, its bare SYN).
+    SynSent { iss: u32 },
+    /// Received a bare SYN (no ACK) while in `SynSent`: a simultaneous open. Our SYN-ACK
+    /// acknowledging the peer's initial receive sequence number `irs` is out; waiting on the peer
+    /// to ACK our SYN in turn.
+    SynReceived { iss: u32, irs: u32 },
+    /// Both sides' SYNs have been sent and acknowledged.
+    Established { snd_una: u32, rcv_nxt: u32 },
+}
+
+/// Flags, of the ones relevant to the handshake, carried by an inbound or outbound segment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SegmentFlags {
+    pub syn: bool,
+    pub ack: bool,
+}
+
+/// A segment to send in response to [`on_segment`], described only by what that decision needs:
+/// flags, sequence number and acknowledgement number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutboundSegment {
+    pub flags: SegmentFlags,
+    pub seq: u32,
+    pub ack: u32,
+}
+
+/// Result of feeding one inbound segment to [`on_segment`]: the resulting state, plus the segment
+/// (if any) that should be sent in response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transition {
+    pub state: ActiveOpenState,
+    pub reply: Option<OutboundSegment>,
+}
+
+//==============================================================================
+// Functions
+//==============================================================================
+
+/// Advances `state` given an inbound segment with sequence number `seq`, acknowledgement number
+/// `ack` (meaningful only when `flags.ack` is set) and `flags`.
+///
+/// Handles the three transitions `connect()` needs to converge on `Established`:
+/// - A normal SYN-ACK acknowledging our ISS: `SynSent` -> `Established` directly.
+/// - A bare SYN (simultaneous open, RFC 793 §3.4): instead of tearing the attempt down, moves
+///   `SynSent` -> `SynReceived`, replying with a SYN-ACK that acknowledges the peer's ISN.
+/// - The ACK of our SYN-ACK while in `SynReceived`: converges to `Established`.
+///
+/// Any other segment leaves `state` unchanged with no reply (the caller's existing retransmission
+/// timer is what re-sends the outstanding SYN/SYN-ACK; this function only reacts to progress).
+pub fn on_segment(state: ActiveOpenState, seq: u32, ack: u32, flags: SegmentFlags) -> Transition {
+    match state {
+        ActiveOpenState::SynSent { iss } => {
+            if flags.syn && flags.ack && ack == iss.wrapping_add(1) {
+                // Normal case: the peer's passive-open SYN-ACK.
+                Transition {
+                    state: ActiveOpenState::Established {
+                        snd_una: iss.wrapping_add(1),
+                        rcv_nxt: seq.wrapping_add(1),
+                    },
+                    reply: Some(OutboundSegment {
+                        flags: SegmentFlags { syn: false, ack: true },
+                        seq: iss.wrapping_add(1),
+                        ack: seq.wrapping_add(1),
+                    }),
+                }
+            } else if flags.syn && !flags.ack {
+                // Simultaneous open: the peer's own `connect()` raced ours, so its first segment is
+                // a bare SYN rather than an ACK of ours.
+                Transition {
+                    state: ActiveOpenState::SynReceived { iss, irs: seq },
+                    reply: Some(OutboundSegment {
+                        flags: SegmentFlags { syn: true, ack: true },
+                        seq: iss,
+                        ack: seq.wrapping_add(1),
+                    }),
+                }
+            } else {
+                Transition { state, reply: None }
+            }
+        },
+        ActiveOpenState::SynReceived { iss, irs } => {
+            if flags.ack && ack == iss.wrapping_add(1) {
+                Transition {
+                    state: ActiveOpenState::Established {
+                        snd_una: iss.wrapping_add(1),
+                        rcv_nxt: irs.wrapping_add(1),
+                    },
+                    reply: None,
+                }
+            } else {
+                Transition { state, reply: None }
+            }
+        },
+        ActiveOpenState::Established { .. } => Transition { state, reply: None },
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn syn_sent_to_established_on_syn_ack() {
+        let state: ActiveOpenState = ActiveOpenState::SynSent { iss: 100 };
+        let t: Transition = on_segment(state, /* seq */ 500, /* ack */ 101, SegmentFlags { syn: true, ack: true });
+        assert_eq!(
+            t.state,
+            ActiveOpenState::Established {
+                snd_una: 101,
+                rcv_nxt: 501
+            }
+        );
+        assert_eq!(
+            t.reply,
+            Some(OutboundSegment {
+                flags: SegmentFlags { syn: false, ack: true },
+                seq: 101,
+                ack: 501,
+            })
+        );
+    }
+
+    /// Both peers `bind()` to a known port and `connect()` at roughly the same time: the first
+    /// segment each side receives is the other's bare SYN, not a SYN-ACK.
+    #[test]
+    fn simultaneous_open_moves_to_syn_received() {
+        let state: ActiveOpenState = ActiveOpenState::SynSent { iss: 100 };
+        let t: Transition = on_segment(state, /* seq */ 700, /* ack */ 0, SegmentFlags { syn: true, ack: false });
+        assert_eq!(t.state, ActiveOpenState::SynReceived { iss: 100, irs: 700 });
+        assert_eq!(
+            t.reply,
+            Some(OutboundSegment {
+                flags: SegmentFlags { syn: true, ack: true },
+                seq: 100,
+                ack: 701,
+            })
+        );
+    }
+
+    #[test]
+    fn syn_received_converges_to_established_on_ack() {
+        let state: ActiveOpenState = ActiveOpenState::SynReceived { iss: 100, irs: 700 };
+        let t: Transition = on_segment(state, /* seq */ 701, /* ack */ 101, SegmentFlags { syn: false, ack: true });
+        assert_eq!(
+            t.state,
+            ActiveOpenState::Established {
+                snd_una: 101,
+                rcv_nxt: 701
+            }
+        );
+        assert_eq!(t.reply, None);
+    }
+
+    #[test]
+    fn unrelated_segment_leaves_syn_sent_unchanged() {
+        let state: ActiveOpenState = ActiveOpenState::SynSent { iss: 100 };
+        let t: Transition = on_segment(state, 0, 0, SegmentFlags { syn: false, ack: false });
+        assert_eq!(t.state, state);
+        assert_eq!(t.reply, None);
+    }
+}
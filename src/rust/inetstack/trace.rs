@@ -0,0 +1,323 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//==============================================================================
+// Imports
+//==============================================================================
+
+use crate::runtime::{
+    fail::Fail,
+    QDesc,
+    QToken,
+};
+use ::std::{
+    fs::File,
+    io::{
+        BufReader,
+        BufWriter,
+        ErrorKind,
+        Read,
+        Write,
+    },
+    path::PathBuf,
+    time::Instant,
+};
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+const FRAME_TAG: u8 = 0;
+const COMPLETION_TAG: u8 = 1;
+
+//==============================================================================
+// Structures
+//==============================================================================
+
+/// Record-and-replay mode for a deterministic [`InetStack`](crate::inetstack::InetStack) run.
+///
+/// Built on top of the existing `rng_seed` configuration: a `Record` run with a fixed seed is
+/// bit-reproducible by a later `Replay` run given the same seed and the trace file it produced,
+/// which turns a one-off TCP state-machine crash (e.g. on a malformed packet) into a fixture that
+/// can be stepped through repeatedly.
+#[derive(Clone, Debug)]
+pub enum TraceMode {
+    /// Appends every inbound frame and delivered completion to the trace file at this path.
+    Record(PathBuf),
+    /// Re-injects the frames previously captured at this path on the same relative schedule.
+    Replay(PathBuf),
+}
+
+/// One recorded event, tagged with the number of nanoseconds since the trace started.
+#[derive(Clone, Debug)]
+enum TraceEvent {
+    /// An inbound frame, exactly as handed to [`InetStack::do_receive`](crate::inetstack::InetStack::do_receive).
+    Frame { offset_nanos: u64, bytes: Vec<u8> },
+    /// A completion delivered to `wait`/`wait_any`, identified by the `Debug` representation of
+    /// its queue token and queue descriptor (trace files are a debugging aid, not re-injected, so
+    /// there is no need to depend on `QToken`/`QDesc`'s internal representation here).
+    Completion { offset_nanos: u64, qt: String, qd: String },
+}
+
+impl TraceEvent {
+    /// Serializes this event as `tag | offset_nanos | payload` onto `w`.
+    fn write(&self, w: &mut impl Write) -> Result<(), Fail> {
+        match self {
+            TraceEvent::Frame { offset_nanos, bytes } => {
+                w.write_all(&[FRAME_TAG]).map_err(io_fail)?;
+                w.write_all(&offset_nanos.to_le_bytes()).map_err(io_fail)?;
+                write_bytes(w, bytes)
+            },
+            TraceEvent::Completion { offset_nanos, qt, qd } => {
+                w.write_all(&[COMPLETION_TAG]).map_err(io_fail)?;
+                w.write_all(&offset_nanos.to_le_bytes()).map_err(io_fail)?;
+                write_bytes(w, qt.as_bytes())?;
+                write_bytes(w, qd.as_bytes())
+            },
+        }
+    }
+
+    /// Deserializes the next event from `r`, or `None` at a clean end-of-file.
+    fn read(r: &mut impl Read) -> Result<Option<Self>, Fail> {
+        let mut tag: [u8; 1] = [0u8; 1];
+        match r.read_exact(&mut tag) {
+            Ok(()) => {},
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(io_fail(e)),
+        }
+        let offset_nanos: u64 = read_u64(r)?;
+        match tag[0] {
+            FRAME_TAG => {
+                let bytes: Vec<u8> = read_bytes(r)?;
+                Ok(Some(TraceEvent::Frame { offset_nanos, bytes }))
+            },
+            COMPLETION_TAG => {
+                let qt: String = String::from_utf8(read_bytes(r)?)
+                    .map_err(|e| Fail::new(libc::EINVAL, &format!("corrupt trace file: {}", e)))?;
+                let qd: String = String::from_utf8(read_bytes(r)?)
+                    .map_err(|e| Fail::new(libc::EINVAL, &format!("corrupt trace file: {}", e)))?;
+                Ok(Some(TraceEvent::Completion { offset_nanos, qt, qd }))
+            },
+            _ => Err(Fail::new(libc::EINVAL, "corrupt trace file: unknown event tag")),
+        }
+    }
+}
+
+fn write_bytes(w: &mut impl Write, bytes: &[u8]) -> Result<(), Fail> {
+    w.write_all(&(bytes.len() as u32).to_le_bytes()).map_err(io_fail)?;
+    w.write_all(bytes).map_err(io_fail)
+}
+
+fn read_bytes(r: &mut impl Read) -> Result<Vec<u8>, Fail> {
+    let len: usize = read_u32(r)? as usize;
+    let mut bytes: Vec<u8> = vec![0u8; len];
+    r.read_exact(&mut bytes).map_err(io_fail)?;
+    Ok(bytes)
+}
+
+fn read_u64(r: &mut impl Read) -> Result<u64, Fail> {
+    let mut buf: [u8; 8] = [0u8; 8];
+    r.read_exact(&mut buf).map_err(io_fail)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_u32(r: &mut impl Read) -> Result<u32, Fail> {
+    let mut buf: [u8; 4] = [0u8; 4];
+    r.read_exact(&mut buf).map_err(io_fail)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn io_fail(e: ::std::io::Error) -> Fail {
+    Fail::new(libc::EIO, &format!("trace I/O error: {}", e))
+}
+
+/// Appends every inbound frame and delivered completion to a binary trace file, timestamped
+/// relative to when recording started.
+pub struct TraceRecorder {
+    start: Instant,
+    writer: BufWriter<File>,
+}
+
+impl TraceRecorder {
+    /// Creates (truncating) the trace file at `path`.
+    pub fn create(path: &PathBuf) -> Result<Self, Fail> {
+        let file: File = File::create(path).map_err(io_fail)?;
+        Ok(Self {
+            start: Instant::now(),
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// Records an inbound frame, verbatim.
+    pub fn record_frame(&mut self, bytes: &[u8]) -> Result<(), Fail> {
+        let event: TraceEvent = TraceEvent::Frame {
+            offset_nanos: self.start.elapsed().as_nanos() as u64,
+            bytes: bytes.to_vec(),
+        };
+        event.write(&mut self.writer)
+    }
+
+    /// Records a completion delivered to `wait`/`wait_any`.
+    pub fn record_completion(&mut self, qt: QToken, qd: QDesc) -> Result<(), Fail> {
+        let event: TraceEvent = TraceEvent::Completion {
+            offset_nanos: self.start.elapsed().as_nanos() as u64,
+            qt: format!("{:?}", qt),
+            qd: format!("{:?}", qd),
+        };
+        event.write(&mut self.writer)
+    }
+
+    /// Flushes buffered events to disk. Callers should invoke this before dropping the recorder at
+    /// the end of a run to avoid losing the tail of the trace.
+    pub fn flush(&mut self) -> Result<(), Fail> {
+        self.writer.flush().map_err(io_fail)
+    }
+}
+
+/// Live trace state for an [`InetStack`](crate::inetstack::InetStack), resolved once from the
+/// configured [`TraceMode`] at construction time.
+pub enum Trace {
+    Recording(TraceRecorder),
+    Replaying(TracePlayer),
+}
+
+impl Trace {
+    /// Resolves `mode` into live trace state, creating or opening its backing file.
+    pub fn new(mode: &TraceMode) -> Result<Self, Fail> {
+        match mode {
+            TraceMode::Record(path) => Ok(Trace::Recording(TraceRecorder::create(path)?)),
+            TraceMode::Replay(path) => Ok(Trace::Replaying(TracePlayer::open(path)?)),
+        }
+    }
+}
+
+/// Re-injects previously captured frames on the same relative schedule they were recorded on,
+/// leaving the scheduler, clock and `rng_seed`-derived RNG to deterministically reproduce the rest
+/// of the original run's behavior.
+pub struct TracePlayer {
+    start: Instant,
+    reader: BufReader<File>,
+    pending: Option<TraceEvent>,
+}
+
+impl TracePlayer {
+    /// Opens a previously recorded trace file at `path`.
+    pub fn open(path: &PathBuf) -> Result<Self, Fail> {
+        let file: File = File::open(path).map_err(io_fail)?;
+        Ok(Self {
+            start: Instant::now(),
+            reader: BufReader::new(file),
+            pending: None,
+        })
+    }
+
+    /// Returns the next recorded frame whose recorded offset has elapsed, draining it from the
+    /// trace so each frame is replayed exactly once. Returns `Ok(None)` when no frame is due yet,
+    /// and skips over recorded completions (they exist for offline diffing against the replay's
+    /// actual output, not for re-injection: `poll_bg_work` re-derives them from the replayed frames).
+    pub fn next_ready_frame(&mut self) -> Result<Option<Vec<u8>>, Fail> {
+        loop {
+            if self.pending.is_none() {
+                self.pending = TraceEvent::read(&mut self.reader)?;
+            }
+            match &self.pending {
+                Some(TraceEvent::Frame { offset_nanos, .. }) if self.start.elapsed().as_nanos() as u64 >= *offset_nanos => {
+                    match self.pending.take() {
+                        Some(TraceEvent::Frame { bytes, .. }) => return Ok(Some(bytes)),
+                        _ => unreachable!(),
+                    }
+                },
+                Some(TraceEvent::Frame { .. }) => return Ok(None),
+                Some(TraceEvent::Completion { .. }) => self.pending = None,
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+//==============================================================================
+// Unit Tests
+//==============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::std::io::Cursor;
+
+    #[test]
+    fn frame_round_trips() {
+        let event: TraceEvent = TraceEvent::Frame {
+            offset_nanos: 1_234_567,
+            bytes: vec![0xde, 0xad, 0xbe, 0xef],
+        };
+        let mut buf: Vec<u8> = Vec::new();
+        event.write(&mut buf).unwrap();
+
+        let mut cursor: Cursor<Vec<u8>> = Cursor::new(buf);
+        match TraceEvent::read(&mut cursor).unwrap() {
+            Some(TraceEvent::Frame { offset_nanos, bytes }) => {
+                assert_eq!(offset_nanos, 1_234_567);
+                assert_eq!(bytes, vec![0xde, 0xad, 0xbe, 0xef]);
+            },
+            other => panic!("expected a Frame event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn completion_round_trips() {
+        let event: TraceEvent = TraceEvent::Completion {
+            offset_nanos: 42,
+            qt: "QToken(7)".to_string(),
+            qd: "QDesc(3)".to_string(),
+        };
+        let mut buf: Vec<u8> = Vec::new();
+        event.write(&mut buf).unwrap();
+
+        let mut cursor: Cursor<Vec<u8>> = Cursor::new(buf);
+        match TraceEvent::read(&mut cursor).unwrap() {
+            Some(TraceEvent::Completion { offset_nanos, qt, qd }) => {
+                assert_eq!(offset_nanos, 42);
+                assert_eq!(qt, "QToken(7)");
+                assert_eq!(qd, "QDesc(3)");
+            },
+            other => panic!("expected a Completion event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn multiple_events_round_trip_in_order() {
+        let frame: TraceEvent = TraceEvent::Frame {
+            offset_nanos: 10,
+            bytes: vec![1, 2, 3],
+        };
+        let completion: TraceEvent = TraceEvent::Completion {
+            offset_nanos: 20,
+            qt: "QToken(1)".to_string(),
+            qd: "QDesc(1)".to_string(),
+        };
+        let mut buf: Vec<u8> = Vec::new();
+        frame.write(&mut buf).unwrap();
+        completion.write(&mut buf).unwrap();
+
+        let mut cursor: Cursor<Vec<u8>> = Cursor::new(buf);
+        assert!(matches!(TraceEvent::read(&mut cursor).unwrap(), Some(TraceEvent::Frame { .. })));
+        assert!(matches!(TraceEvent::read(&mut cursor).unwrap(), Some(TraceEvent::Completion { .. })));
+        assert!(TraceEvent::read(&mut cursor).unwrap().is_none());
+    }
+
+    #[test]
+    fn read_on_clean_eof_returns_none() {
+        let mut cursor: Cursor<Vec<u8>> = Cursor::new(Vec::new());
+        assert!(TraceEvent::read(&mut cursor).unwrap().is_none());
+    }
+
+    #[test]
+    fn read_rejects_unknown_tag() {
+        // A single byte that isn't FRAME_TAG or COMPLETION_TAG, followed by enough bytes to look
+        // like a valid offset so the failure comes from the tag check, not a truncated read.
+        let mut buf: Vec<u8> = vec![0xff];
+        buf.extend_from_slice(&0u64.to_le_bytes());
+        let mut cursor: Cursor<Vec<u8>> = Cursor::new(buf);
+        assert!(TraceEvent::read(&mut cursor).is_err());
+    }
+}